@@ -2,17 +2,27 @@ use bevy::{
     input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     math::vec2,
     prelude::*,
+    render::camera::NormalizedRenderTarget,
+    window::PrimaryWindow,
 };
 
 pub struct CameraControllerPlugin;
 #[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
 pub struct CameraMovementSet;
+/// Updates [`WorldCursor`], anything reading it should run after this
+#[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
+pub struct WorldCursorSet;
 
 impl Plugin for CameraControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<WorldCursor>().add_systems(
             Update,
-            (zoom_camera, move_camera).chain().in_set(CameraMovementSet),
+            (
+                (zoom_camera, move_camera).chain().in_set(CameraMovementSet),
+                update_world_cursor
+                    .in_set(WorldCursorSet)
+                    .after(CameraMovementSet),
+            ),
         );
     }
 }
@@ -44,6 +54,73 @@ impl Default for CameraController {
     }
 }
 
+/// Where the cursor points in the world, updated once the camera has moved this frame
+#[derive(Resource, Default, Clone, PartialEq, Debug)]
+pub struct WorldCursor {
+    /// None when the cursor is outside every 2D viewport
+    pub position: Option<Vec2>,
+    /// Window the cursor is in
+    pub window: Option<Entity>,
+    /// Camera of the viewport the cursor is in, the topmost one if they overlap
+    pub camera: Option<Entity>,
+    /// World units covered by one logical pixel of that viewport
+    pub units_per_pixel: f32,
+}
+
+fn update_world_cursor(
+    mut cursor: ResMut<WorldCursor>,
+    primary_query: Query<Entity, With<PrimaryWindow>>,
+    window_query: Query<(Entity, &Window)>,
+    camera_query: Query<(Entity, &Camera, &Transform), With<OrthographicProjection>>,
+) {
+    let primary = primary_query.get_single().ok();
+    let mut found = None;
+    for (window_entity, window) in window_query.iter() {
+        let Some(position) = window.cursor_position() else {
+            continue;
+        };
+        found = camera_query
+            .iter()
+            .filter(|(_, camera, _)| {
+                camera.is_active
+                    && matches!(camera.target.normalize(primary),
+                        Some(NormalizedRenderTarget::Window(target)) if target.entity() == window_entity)
+            })
+            .filter_map(|(entity, camera, transform)| {
+                let viewport = camera.logical_viewport_rect()?;
+                if !viewport.contains(position) {
+                    return None;
+                }
+                let position = position - viewport.min;
+                // the global transform is only propagated at the end of the frame, the cameras have no parent
+                let transform = GlobalTransform::from(*transform);
+                let world = camera.viewport_to_world_2d(&transform, position)?;
+                let next = camera.viewport_to_world_2d(&transform, position + Vec2::X)?;
+                Some((camera.order, window_entity, entity, world, world.distance(next)))
+            })
+            .max_by_key(|(order, ..)| *order);
+        if found.is_some() {
+            break;
+        }
+    }
+    let new = match found {
+        Some((_, window, camera, position, units_per_pixel)) => WorldCursor {
+            position: Some(position),
+            window: Some(window),
+            camera: Some(camera),
+            units_per_pixel,
+        },
+        None => WorldCursor {
+            units_per_pixel: cursor.units_per_pixel,
+            ..default()
+        },
+    };
+    // only write when it moves, so change detection means something
+    if *cursor != new {
+        *cursor = new;
+    }
+}
+
 fn move_camera(
     time: Res<Time>,
     mouse: Res<ButtonInput<MouseButton>>,
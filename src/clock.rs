@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+
+pub struct ClockPlugin;
+#[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
+pub struct ClockSet;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimClock>()
+            .add_systems(Update, advance_clock.in_set(ClockSet));
+    }
+}
+
+/// Simulation time, separate from `Time` so it can be paused, sped up and stepped
+#[derive(Resource)]
+pub struct SimClock {
+    pub paused: bool,
+    /// Simulated seconds per real second
+    pub scale: f32,
+    /// Simulated seconds advanced by a single step while paused
+    pub step_size: f32,
+    step_requested: bool,
+    delta: f32,
+    elapsed: f32,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            scale: 1.,
+            step_size: 1. / 30.,
+            step_requested: false,
+            delta: 0.,
+            elapsed: 0.,
+        }
+    }
+}
+
+impl SimClock {
+    pub const MIN_SCALE: f32 = 1. / 16.;
+    pub const MAX_SCALE: f32 = 64.;
+
+    /// Simulated seconds since last frame, 0 while paused
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Advance by `step_size` on the next frame, only has an effect while paused
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+    }
+}
+
+fn advance_clock(time: Res<Time>, mut clock: ResMut<SimClock>) {
+    clock.delta = if !clock.paused {
+        time.delta_seconds() * clock.scale
+    } else if clock.step_requested {
+        clock.step_size
+    } else {
+        0.
+    };
+    clock.step_requested = false;
+    clock.elapsed += clock.delta;
+}
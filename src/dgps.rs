@@ -0,0 +1,159 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    floppy::FloppyBody,
+    gps::{solve_position, solve_position_and_bias, RangeMeasurement},
+    moveable::Moveable,
+    receiver::ClockOffset,
+    satellite::{measured_range, RangeError, RangeNoise, RangeSampleSet, Satellite},
+    Settings, SimulationSet,
+};
+
+pub struct DgpsPlugin;
+
+impl Plugin for DgpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DgpsCorrections>().add_systems(
+            Update,
+            (
+                update_base_station_visibility,
+                (update_corrections, update_dgps_estimates).chain(),
+            )
+                .in_set(SimulationSet)
+                .after(RangeSampleSet),
+        );
+    }
+}
+
+/// Reference receiver at a surveyed position, its own transform is taken as the known truth
+#[derive(Component)]
+pub struct BaseStation;
+
+/// Correction for each satellite the base station sees, true range minus measured range.
+/// Only the systematic errors are shared with the rovers, the base station's noise is its own and adds to theirs
+#[derive(Resource, Default)]
+pub struct DgpsCorrections(pub HashMap<Entity, f32>);
+
+/// Fix computed from ranges corrected by the base station
+#[derive(Component, Default)]
+pub struct DgpsEstimate {
+    pub position: Option<Vec2>,
+}
+
+pub fn spawn_base_station(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: asset_server.load("BaseStation.png"),
+                transform: Transform::from_translation(position.extend(0.4)),
+                ..default()
+            },
+            Moveable {
+                radius: 60.,
+                velocity: Vec2::ZERO,
+            },
+            BaseStation,
+            RangeNoise::default(),
+        ))
+        .id()
+}
+
+fn update_base_station_visibility(
+    settings: Res<Settings>,
+    mut query: Query<&mut Visibility, With<BaseStation>>,
+) {
+    for mut vis in query.iter_mut() {
+        *vis = if settings.dgps_enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    }
+}
+
+fn update_corrections(
+    settings: Res<Settings>,
+    mut corrections: ResMut<DgpsCorrections>,
+    base_query: Query<(&Transform, &RangeNoise), With<BaseStation>>,
+    satellite_query: Query<(Entity, &Transform, Option<&RangeError>), With<Satellite>>,
+) {
+    corrections.0.clear();
+    if !settings.dgps_enabled {
+        return;
+    }
+    let Ok((base, noise)) = base_query.get_single() else {
+        return;
+    };
+    let base = base.translation.xy();
+    for (entity, satellite, error) in satellite_query.iter() {
+        let p = satellite.translation.xy();
+        if !settings.in_view(base, p) {
+            continue;
+        }
+        let measured = measured_range(
+            p,
+            error,
+            noise.get(entity),
+            base,
+            settings.local_up(base),
+            settings.clock_offset(None),
+        );
+        corrections.0.insert(entity, p.distance(base) - measured);
+    }
+}
+
+fn update_dgps_estimates(
+    settings: Res<Settings>,
+    corrections: Res<DgpsCorrections>,
+    mut player_query: Query<
+        (
+            &Transform,
+            Option<&ClockOffset>,
+            &RangeNoise,
+            &mut DgpsEstimate,
+        ),
+        With<FloppyBody>,
+    >,
+    satellite_query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&RangeError>,
+            &InheritedVisibility,
+        ),
+        With<Satellite>,
+    >,
+) {
+    for (player, offset, noise, mut estimate) in player_query.iter_mut() {
+        let player = player.translation.xy();
+        let offset = settings.clock_offset(offset);
+        let up = settings.local_up(player);
+        // only satellites both receivers see can be corrected
+        let measurements: Vec<_> = satellite_query
+            .iter()
+            .filter(|(_, satellite, _, vis)| {
+                vis.get() && settings.in_view(player, satellite.translation.xy())
+            })
+            .filter_map(|(entity, satellite, error, _)| {
+                let p = satellite.translation.xy();
+                let noise = noise.get(entity);
+                Some(RangeMeasurement {
+                    satellite: p,
+                    range: measured_range(p, error, noise, player, up, offset)
+                        + corrections.0.get(&entity)?,
+                    variance: error.map_or(1., |e| e.variance()),
+                })
+            })
+            .collect();
+        let initial = estimate.position.unwrap_or(player);
+        estimate.position = if settings.solve_clock_bias {
+            solve_position_and_bias(&measurements, initial).map(|(pos, _)| pos)
+        } else {
+            solve_position(&measurements, initial)
+        };
+    }
+}
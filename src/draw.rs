@@ -0,0 +1,202 @@
+use bevy::{math::vec2, prelude::*};
+
+use crate::{
+    dgps::DgpsEstimate,
+    gps::{confidence_ellipse, range_difference_hyperbola},
+    receiver::{ClockOffset, ReceiverColor},
+    satellite::{measured_range, RangeError, RangeNoise, RangeSampleSet, Satellite},
+    scatter::FixScatter,
+    solver::PositionEstimate,
+    track::Track,
+    Character, GraphVisibility, Settings, SimulationSet,
+};
+
+/// Gizmo drawing of the simulation state, everything here is left out of the headless app
+pub struct DrawPlugin;
+
+impl Plugin for DrawPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                draw_earth,
+                draw_satellite_circle,
+                draw_estimates,
+                draw_dgps_estimates,
+                draw_scatter,
+                draw_tracks,
+                shade_hidden_satellites,
+            )
+                .after(RangeSampleSet)
+                .after(SimulationSet),
+        );
+    }
+}
+
+fn draw_satellite_circle(
+    mut gizmos: Gizmos,
+    settings: Res<Settings>,
+    player_query: Query<(
+        &Transform,
+        Option<&ClockOffset>,
+        &RangeNoise,
+        &ReceiverColor,
+    )>,
+    satellite_query: Query<(Entity, &Transform, Option<&RangeError>), With<Satellite>>,
+) {
+    for (player, offset, noise, color) in player_query.iter() {
+        let player = player.translation.xy();
+        let offset = settings.clock_offset(offset);
+        let up = settings.local_up(player);
+        // the ranges this receiver measures, each satellite with its own noise
+        let satellites: Vec<_> = satellite_query
+            .iter()
+            .map(|(entity, satellite, error)| {
+                let p = satellite.translation.xy();
+                let noise = noise.get(entity);
+                (p, measured_range(p, error, noise, player, up, offset))
+            })
+            .filter(|(p, _)| settings.in_view(player, *p))
+            .collect();
+        let mut last = None;
+        for &(p, range) in satellites.iter() {
+            // the clock offset cancels out in the range differences
+            let dst = range - offset;
+            // the offset can shrink a range below zero, there is no circle to draw then
+            if settings.ranges_visible && range > 0. {
+                gizmos.circle_2d(p, range, color.0).segments(256);
+            }
+            if settings.graph_visibility == GraphVisibility::Some {
+                if let Some((lst, lstr)) = last {
+                    darw_arcs(&mut gizmos, &settings, p, dst, lst, lstr)
+                } else {
+                    gizmos.circle_2d(p, 200., Color::RED);
+                }
+            }
+            last = Some((p, dst))
+        }
+        if settings.graph_visibility != GraphVisibility::All {
+            continue;
+        }
+        for (i, &(s1, r1)) in satellites.iter().enumerate() {
+            for &(s2, r2) in satellites[i + 1..].iter() {
+                darw_arcs(&mut gizmos, &settings, s1, r1 - offset, s2, r2 - offset);
+            }
+        }
+    }
+}
+
+fn draw_earth(mut gizmos: Gizmos, settings: Res<Settings>) {
+    if settings.earth_enabled {
+        gizmos
+            .circle_2d(
+                settings.earth_centre,
+                settings.earth_radius,
+                Color::rgb(0.3, 0.6, 0.3),
+            )
+            .segments(512);
+    }
+}
+
+/// Greys out the satellites the character cannot see
+fn shade_hidden_satellites(
+    settings: Res<Settings>,
+    character_query: Query<&Transform, With<Character>>,
+    mut satellite_query: Query<(&Transform, &mut Sprite), With<Satellite>>,
+) {
+    let Ok(character) = character_query.get_single() else {
+        return;
+    };
+    let character = character.translation.xy();
+    for (satellite, mut sprite) in satellite_query.iter_mut() {
+        let color = if settings.in_view(character, satellite.translation.xy()) {
+            Color::WHITE
+        } else {
+            Color::GRAY
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+fn darw_arcs(gizmos: &mut Gizmos, settings: &Settings, p1: Vec2, r1: f32, p2: Vec2, r2: f32) {
+    if let Some(points) = range_difference_hyperbola(
+        p1,
+        r1,
+        p2,
+        r2,
+        settings.hyperbola_extent,
+        settings.hyperbola_samples,
+    ) {
+        gizmos.linestrip_2d(points, Color::GREEN);
+    }
+}
+
+fn draw_estimates(mut gizmos: Gizmos, query: Query<&PositionEstimate>) {
+    for estimate in query.iter() {
+        let Some(pos) = estimate.position else {
+            continue;
+        };
+        let size = 20.;
+        gizmos.circle_2d(pos, size, Color::YELLOW);
+        gizmos.line_2d(pos - vec2(size, 0.), pos + vec2(size, 0.), Color::YELLOW);
+        gizmos.line_2d(pos - vec2(0., size), pos + vec2(0., size), Color::YELLOW);
+        if let Some(covariance) = estimate.covariance {
+            let (angle, half_size) = confidence_ellipse(covariance, 0.95);
+            if half_size.is_finite() {
+                gizmos
+                    .ellipse_2d(pos, angle, half_size, Color::ORANGE)
+                    .segments(64);
+            }
+        }
+    }
+}
+
+/// Corrected fixes as a green x, joined to the uncorrected fix to show the improvement
+fn draw_dgps_estimates(mut gizmos: Gizmos, query: Query<(&PositionEstimate, &DgpsEstimate)>) {
+    for (estimate, dgps) in query.iter() {
+        let Some(pos) = dgps.position else {
+            continue;
+        };
+        let size = 14.;
+        let color = Color::rgb(0.2, 1., 0.4);
+        gizmos.line_2d(pos - vec2(size, size), pos + vec2(size, size), color);
+        gizmos.line_2d(pos - vec2(size, -size), pos + vec2(size, -size), color);
+        if let Some(uncorrected) = estimate.position {
+            gizmos.line_2d(uncorrected, pos, color.with_a(0.4));
+        }
+    }
+}
+
+fn draw_scatter(mut gizmos: Gizmos, query: Query<&FixScatter>) {
+    for scatter in query.iter() {
+        for sample in scatter.samples.iter() {
+            gizmos
+                .circle_2d(*sample, 3., Color::rgba(1., 0.2, 0.2, 0.6))
+                .segments(6);
+        }
+    }
+}
+
+/// Recorded true route in white and the fixes along it in yellow, with the error every few points
+fn draw_tracks(mut gizmos: Gizmos, query: Query<&Track>) {
+    for track in query.iter() {
+        gizmos.linestrip_2d(
+            track.points.iter().map(|p| p.truth),
+            Color::rgba(1., 1., 1., 0.8),
+        );
+        // the fix can drop out, so the estimate line is split where it does
+        for run in track.points.split(|p| p.estimate.is_none()) {
+            gizmos.linestrip_2d(
+                run.iter().filter_map(|p| p.estimate),
+                Color::rgba(1., 1., 0., 0.8),
+            );
+        }
+        for point in track.points.iter().step_by(10) {
+            if let Some(estimate) = point.estimate {
+                gizmos.line_2d(point.truth, estimate, Color::rgba(1., 0.3, 0.3, 0.6));
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::{
+    floppy::FloppyBody, moveable::MoveableSet, satellite::RangeSampleSet, Settings, SimulationSet,
+};
+
+pub struct EarthPlugin;
+
+impl Plugin for EarthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            place_receivers_on_earth
+                .after(MoveableSet)
+                .before(RangeSampleSet)
+                .before(SimulationSet),
+        );
+    }
+}
+
+/// Keeps receivers standing on the surface while the earth is enabled
+fn place_receivers_on_earth(
+    settings: Res<Settings>,
+    mut query: Query<&mut Transform, With<FloppyBody>>,
+) {
+    for mut transform in query.iter_mut() {
+        let (translation, rotation) = if settings.earth_enabled {
+            let up = (transform.translation.xy() - settings.earth_centre)
+                .try_normalize()
+                .unwrap_or(Vec2::Y);
+            (
+                (settings.earth_centre + up * settings.earth_radius)
+                    .extend(transform.translation.z),
+                Quat::from_rotation_z(up.to_angle() - FRAC_PI_2),
+            )
+        } else {
+            (transform.translation, Quat::IDENTITY)
+        };
+        // only touch the transform when it moves, other systems watch it for changes
+        if transform.translation != translation || transform.rotation != rotation {
+            transform.translation = translation;
+            transform.rotation = rotation;
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Mapping from the world plane to latitude and longitude, and writers for GPX and NMEA 0183
+
+use std::fmt::Write;
+
+use bevy::prelude::*;
+
+use crate::track::{Track, TrackPoint};
+
+const EARTH_RADIUS_METRES: f64 = 6_371_000.;
+const METRES_PER_SECOND_TO_KNOTS: f64 = 1. / 0.514444;
+
+/// Places the world origin on the real earth
+///
+/// The world is a vertical slice along the east-west line through the origin, +x is east and +y is
+/// height, the same up the ionosphere and elevation mask use. Every point is at the origin latitude.
+#[derive(Resource, Clone)]
+pub struct GeoReference {
+    pub origin_latitude: f64,
+    pub origin_longitude: f64,
+    pub metres_per_unit: f64,
+    /// Unix time in seconds at simulated time zero
+    pub start_time: f64,
+}
+
+impl Default for GeoReference {
+    fn default() -> Self {
+        Self {
+            origin_latitude: 55.6761,
+            origin_longitude: 12.5683,
+            metres_per_unit: 1.,
+            // 2024-01-01 12:00:00 UTC
+            start_time: 1_704_110_400.,
+        }
+    }
+}
+
+/// A moment in UTC, split up the way GPX and NMEA write it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct UtcTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f64,
+}
+
+impl GeoReference {
+    /// Latitude and longitude in degrees, flat earth approximation around the origin
+    pub fn to_lat_lon(&self, position: Vec2) -> (f64, f64) {
+        let east = position.x as f64 * self.metres_per_unit;
+        let longitude = self.origin_longitude + (east / self.metres_per_radian()).to_degrees();
+        (self.origin_latitude, longitude)
+    }
+
+    /// Height in metres above the origin
+    pub fn altitude(&self, position: Vec2) -> f64 {
+        position.y as f64 * self.metres_per_unit
+    }
+
+    /// World x of a longitude, the inverse of [`GeoReference::to_lat_lon`]
+    pub fn east_of(&self, longitude: f64) -> f32 {
+        let east = (longitude - self.origin_longitude).to_radians() * self.metres_per_radian();
+        (east / self.metres_per_unit) as f32
+    }
+
+    // metres per radian of longitude along the origin latitude
+    fn metres_per_radian(&self) -> f64 {
+        EARTH_RADIUS_METRES * self.origin_latitude.to_radians().cos()
+    }
+
+    /// Rounded to the hundredths of a second that get written out
+    pub fn utc(&self, time: f32) -> UtcTime {
+        // rounded before splitting, so 59.999 seconds carries into the minute instead of printing 60.00
+        let unix = ((self.start_time + time as f64) * 100.).round() / 100.;
+        let days = (unix / 86400.).floor();
+        let seconds = unix - days * 86400.;
+        let (year, month, day) = civil_from_days(days as i64);
+        UtcTime {
+            year,
+            month,
+            day,
+            hour: (seconds / 3600.) as u32,
+            minute: (seconds % 3600. / 60.) as u32,
+            second: seconds % 60.,
+        }
+    }
+}
+
+// days since 1970-01-01 to a date, from http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// GPX 1.1 document with the true and estimated route of every track
+pub fn gpx(reference: &GeoReference, tracks: &[&Track]) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(
+        "<gpx version=\"1.1\" creator=\"bevy_gps\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    for (i, track) in tracks.iter().enumerate() {
+        out.push_str(&format!(
+            "  <trk>\n    <name>receiver {} truth</name>\n",
+            i + 1
+        ));
+        write_segment(
+            &mut out,
+            reference,
+            track.points.iter().map(|p| (p.time, p.truth)),
+        );
+        out.push_str("  </trk>\n");
+        out.push_str(&format!(
+            "  <trk>\n    <name>receiver {} estimate</name>\n",
+            i + 1
+        ));
+        // a new segment wherever the fix dropped out
+        for run in track.points.split(|p| p.estimate.is_none()) {
+            if !run.is_empty() {
+                write_segment(
+                    &mut out,
+                    reference,
+                    run.iter().filter_map(|p| Some((p.time, p.estimate?))),
+                );
+            }
+        }
+        out.push_str("  </trk>\n");
+    }
+    out.push_str("</gpx>\n");
+    out
+}
+
+fn write_segment(
+    out: &mut String,
+    reference: &GeoReference,
+    points: impl Iterator<Item = (f32, Vec2)>,
+) {
+    out.push_str("    <trkseg>\n");
+    for (time, position) in points {
+        let (lat, lon) = reference.to_lat_lon(position);
+        let t = reference.utc(time);
+        let _ = writeln!(
+            out,
+            "      <trkpt lat=\"{:.7}\" lon=\"{:.7}\"><ele>{:.2}</ele><time>{:04}-{:02}-{:02}T{:02}:{:02}:{:05.2}Z</time></trkpt>",
+            lat,
+            lon,
+            reference.altitude(position),
+            t.year,
+            t.month,
+            t.day,
+            t.hour,
+            t.minute,
+            t.second
+        );
+    }
+    out.push_str("    </trkseg>\n");
+}
+
+/// XOR of every byte between `$` and `*`
+pub fn nmea_checksum(body: &str) -> u8 {
+    body.bytes().fold(0, |acc, b| acc ^ b)
+}
+
+fn sentence(body: String) -> String {
+    format!("${}*{:02X}\r\n", body, nmea_checksum(&body))
+}
+
+fn nmea_time(t: &UtcTime) -> String {
+    format!("{:02}{:02}{:05.2}", t.hour, t.minute, t.second)
+}
+
+/// Latitude as ddmm.mmmm,N and longitude as dddmm.mmmm,E
+fn nmea_lat_lon(latitude: f64, longitude: f64) -> String {
+    let part = |value: f64, width: usize| {
+        // rounded before splitting, so 59.99999 minutes carries into the degrees instead of printing 60
+        let minutes = (value.abs() * 60. * 1e4).round() as u64;
+        format!(
+            "{:0width$}{:07.4}",
+            minutes / 600_000,
+            (minutes % 600_000) as f64 / 1e4,
+            width = width
+        )
+    };
+    format!(
+        "{},{},{},{}",
+        part(latitude, 2),
+        if latitude < 0. { 'S' } else { 'N' },
+        part(longitude, 3),
+        if longitude < 0. { 'W' } else { 'E' }
+    )
+}
+
+/// `$GPGGA` fix sentence, an empty position marks a missing fix
+pub fn gga(
+    reference: &GeoReference,
+    time: f32,
+    position: Option<Vec2>,
+    satellites: usize,
+    hdop: Option<f32>,
+) -> String {
+    let t = reference.utc(time);
+    let (fix, quality, altitude) = match position {
+        Some(position) => {
+            let (lat, lon) = reference.to_lat_lon(position);
+            (
+                nmea_lat_lon(lat, lon),
+                1,
+                format!("{:.1}", reference.altitude(position)),
+            )
+        }
+        None => (",,,".to_string(), 0, String::new()),
+    };
+    let hdop = hdop.map_or(String::new(), |h| format!("{:.1}", h));
+    sentence(format!(
+        "GPGGA,{},{},{},{:02},{},{},M,0.0,M,,",
+        nmea_time(&t),
+        fix,
+        quality,
+        satellites,
+        hdop,
+        altitude
+    ))
+}
+
+/// `$GPRMC` sentence with speed in knots and course in degrees from north, only the east-west motion counts
+pub fn rmc(reference: &GeoReference, time: f32, position: Option<Vec2>, velocity: Vec2) -> String {
+    let t = reference.utc(time);
+    let (status, fix, mode) = match position {
+        Some(position) => {
+            let (lat, lon) = reference.to_lat_lon(position);
+            ('A', nmea_lat_lon(lat, lon), 'A')
+        }
+        None => ('V', ",,,".to_string(), 'N'),
+    };
+    let speed = velocity.x.abs() as f64 * reference.metres_per_unit * METRES_PER_SECOND_TO_KNOTS;
+    let course = if velocity.x < 0. { 270. } else { 90. };
+    sentence(format!(
+        "GPRMC,{},{},{},{:.2},{:.1},{:02}{:02}{:02},,,{}",
+        nmea_time(&t),
+        status,
+        fix,
+        speed,
+        course,
+        t.day,
+        t.month,
+        t.year.rem_euclid(100),
+        mode
+    ))
+}
+
+/// A GGA and RMC sentence for every point, following the fix or the true position
+pub fn nmea(reference: &GeoReference, points: &[TrackPoint], use_estimate: bool) -> String {
+    let position = |p: &TrackPoint| {
+        if use_estimate {
+            p.estimate
+        } else {
+            Some(p.truth)
+        }
+    };
+    let mut out = String::new();
+    for (i, point) in points.iter().enumerate() {
+        let velocity = match (
+            i.checked_sub(1).and_then(|i| points.get(i)),
+            position(point),
+        ) {
+            (Some(last), Some(pos)) => match position(last) {
+                Some(last_pos) if point.time > last.time => {
+                    (pos - last_pos) / (point.time - last.time)
+                }
+                _ => Vec2::ZERO,
+            },
+            _ => Vec2::ZERO,
+        };
+        out.push_str(&gga(
+            reference,
+            point.time,
+            position(point),
+            point.satellites,
+            point.hdop,
+        ));
+        out.push_str(&rmc(reference, point.time, position(point), velocity));
+    }
+    out
+}
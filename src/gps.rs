@@ -0,0 +1,315 @@
+//! Ranging and positioning math, kept free of ECS and rendering so it can be tested on its own
+
+use bevy::math::{vec2, Mat2, Mat3, Mat4, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
+
+/// A single range measurement to a satellite at a known position
+#[derive(Clone, Copy)]
+pub struct RangeMeasurement {
+    pub satellite: Vec2,
+    pub range: f32,
+    /// Expected variance of `range`, used to weigh it in the covariance
+    pub variance: f32,
+}
+
+const MAX_ITERATIONS: usize = 20;
+const CONVERGED: f32 = 1e-3;
+
+/// Gauss-Newton least squares fit of the position to the measured ranges.
+/// Returns `None` if there are too few satellites or the geometry is degenerate
+pub fn solve_position(measurements: &[RangeMeasurement], initial: Vec2) -> Option<Vec2> {
+    if measurements.len() < 2 {
+        return None;
+    }
+    let mut pos = initial;
+    for _ in 0..MAX_ITERATIONS {
+        // normal equations, (H^T H) dx = H^T r
+        let mut hth = Mat2::ZERO;
+        let mut htr = Vec2::ZERO;
+        for m in measurements {
+            let diff = pos - m.satellite;
+            let dst = diff.length();
+            if dst < f32::EPSILON {
+                continue;
+            }
+            let h = diff / dst;
+            hth += Mat2::from_cols(h * h.x, h * h.y);
+            htr += h * (m.range - dst);
+        }
+        if hth.determinant().abs() < 1e-6 {
+            return None;
+        }
+        let step = hth.inverse() * htr;
+        pos += step;
+        if !pos.is_finite() {
+            return None;
+        }
+        if step.length() < CONVERGED {
+            break;
+        }
+    }
+    Some(pos)
+}
+
+/// Like [`solve_position`] but also treats a clock error shared by all ranges as an unknown.
+/// Needs at least three satellites. Returns the position and the recovered bias
+pub fn solve_position_and_bias(
+    measurements: &[RangeMeasurement],
+    initial: Vec2,
+) -> Option<(Vec2, f32)> {
+    if measurements.len() < 3 {
+        return None;
+    }
+    let mut state = initial.extend(0.);
+    for _ in 0..MAX_ITERATIONS {
+        let pos = state.xy();
+        let mut hth = Mat3::ZERO;
+        let mut htr = Vec3::ZERO;
+        for m in measurements {
+            let diff = pos - m.satellite;
+            let dst = diff.length();
+            if dst < f32::EPSILON {
+                continue;
+            }
+            let h = (diff / dst).extend(1.);
+            hth += Mat3::from_cols(h * h.x, h * h.y, h * h.z);
+            htr += h * (m.range - dst - state.z);
+        }
+        if hth.determinant().abs() < 1e-6 {
+            return None;
+        }
+        let step = hth.inverse() * htr;
+        state += step;
+        if !state.is_finite() {
+            return None;
+        }
+        if step.length() < CONVERGED {
+            break;
+        }
+    }
+    Some((state.xy(), state.z))
+}
+
+/// A range measurement to a satellite in the 3D view
+#[derive(Clone, Copy)]
+pub struct RangeMeasurement3d {
+    pub satellite: Vec3,
+    pub range: f32,
+}
+
+/// Gauss-Newton fit of a 3D position, and the clock bias when `solve_bias` is set.
+/// Needs three satellites, or four with the bias, and returns the position and the bias
+pub fn solve_position_3d(
+    measurements: &[RangeMeasurement3d],
+    initial: Vec3,
+    solve_bias: bool,
+) -> Option<(Vec3, f32)> {
+    if measurements.len() < 3 + solve_bias as usize {
+        return None;
+    }
+    let mut state = initial.extend(0.);
+    for _ in 0..MAX_ITERATIONS {
+        let pos = state.xyz();
+        let mut hth = Mat4::ZERO;
+        let mut htr = Vec4::ZERO;
+        for m in measurements {
+            let diff = pos - m.satellite;
+            let dst = diff.length();
+            if dst < f32::EPSILON {
+                continue;
+            }
+            let h = (diff / dst).extend(solve_bias as u8 as f32);
+            hth += Mat4::from_cols(h * h.x, h * h.y, h * h.z, h * h.w);
+            htr += h * (m.range - dst - state.w);
+        }
+        if !solve_bias {
+            // keeps the bias at zero without changing the position part
+            hth.w_axis.w = 1.;
+        }
+        if hth.determinant().abs() < 1e-6 {
+            return None;
+        }
+        let step = hth.inverse() * htr;
+        state += step;
+        if !state.is_finite() {
+            return None;
+        }
+        if step.length() < CONVERGED {
+            break;
+        }
+    }
+    Some((state.xyz(), state.w))
+}
+
+/// Circle where two spheres meet, as its centre, the normal of its plane and its radius.
+/// `None` when the spheres do not touch or share a centre
+pub fn sphere_intersect(p1: Vec3, r1: f32, p2: Vec3, r2: f32) -> Option<(Vec3, Vec3, f32)> {
+    let d = p1.distance(p2);
+    if d < f32::EPSILON || d > r1 + r2 || d < (r1 - r2).abs() {
+        return None;
+    }
+    // same construction as the circles, rotated around the line between the centres
+    let a = (r1.powi(2) - r2.powi(2) + d.powi(2)) / (2.0 * d);
+    let normal = (p2 - p1) / d;
+    Some((
+        p1 + normal * a,
+        normal,
+        (r1.powi(2) - a.powi(2)).max(0.).sqrt(),
+    ))
+}
+
+/// Dilution of precision for a receiver, how much the satellite geometry amplifies range errors
+#[derive(Clone, Copy)]
+pub struct Dop {
+    pub gdop: f32,
+    pub hdop: f32,
+    pub tdop: f32,
+}
+
+/// (H^T H)^-1 for the position and clock unknowns, unit range variance.
+/// `None` with fewer than three satellites or when they are lined up with the receiver
+pub fn geometry_cofactor(satellites: &[Vec2], receiver: Vec2) -> Option<Mat3> {
+    if satellites.len() < 3 {
+        return None;
+    }
+    let mut hth = Mat3::ZERO;
+    for satellite in satellites {
+        let h = (receiver - *satellite).try_normalize()?.extend(1.);
+        hth += Mat3::from_cols(h * h.x, h * h.y, h * h.z);
+    }
+    if hth.determinant().abs() < 1e-6 {
+        return None;
+    }
+    Some(hth.inverse())
+}
+
+pub fn dilution_of_precision(satellites: &[Vec2], receiver: Vec2) -> Option<Dop> {
+    let q = geometry_cofactor(satellites, receiver)?;
+    Some(Dop {
+        gdop: (q.x_axis.x + q.y_axis.y + q.z_axis.z).sqrt(),
+        hdop: (q.x_axis.x + q.y_axis.y).sqrt(),
+        tdop: q.z_axis.z.sqrt(),
+    })
+}
+
+/// Covariance of a fix at `position`, weighting each measurement by its variance.
+/// Includes the uncertainty added by solving for the clock when `solve_bias` is set
+pub fn position_covariance(
+    measurements: &[RangeMeasurement],
+    position: Vec2,
+    solve_bias: bool,
+) -> Option<Mat2> {
+    let mut hth = Mat3::ZERO;
+    for m in measurements {
+        let h = (position - m.satellite).try_normalize()?.extend(1.);
+        let weight = 1. / m.variance.max(f32::EPSILON);
+        hth += Mat3::from_cols(h * h.x, h * h.y, h * h.z) * weight;
+    }
+    if solve_bias {
+        if hth.determinant().abs() < 1e-12 {
+            return None;
+        }
+        let q = hth.inverse();
+        return Some(Mat2::from_cols(q.x_axis.xy(), q.y_axis.xy()));
+    }
+    let hth = Mat2::from_cols(hth.x_axis.xy(), hth.y_axis.xy());
+    if hth.determinant().abs() < 1e-12 {
+        return None;
+    }
+    Some(hth.inverse())
+}
+
+/// Rotation and half axes of the ellipse containing `probability` of the fixes for a covariance
+pub fn confidence_ellipse(covariance: Mat2, probability: f32) -> (f32, Vec2) {
+    let a = covariance.x_axis.x;
+    let b = covariance.x_axis.y;
+    let c = covariance.y_axis.y;
+    let mean = (a + c) * 0.5;
+    let spread = (((a - c) * 0.5).powi(2) + b * b).sqrt();
+    let angle = 0.5 * (2. * b).atan2(a - c);
+    // chi-squared with two degrees of freedom has a closed form quantile
+    let scale = (-2. * (1. - probability).ln()).sqrt();
+    let half_size = vec2(
+        (mean + spread).max(0.).sqrt(),
+        (mean - spread).max(0.).sqrt(),
+    ) * scale;
+    (angle, half_size)
+}
+
+/// Whether the straight line from `receiver` to `satellite` clears a planet at `centre`.
+/// Grazing the surface, like a receiver standing on it, still counts as clear
+pub fn line_of_sight(receiver: Vec2, satellite: Vec2, centre: Vec2, radius: f32) -> bool {
+    line_of_sight_3d(
+        receiver.extend(0.),
+        satellite.extend(0.),
+        centre.extend(0.),
+        radius,
+    )
+}
+
+/// [`line_of_sight`] past a sphere
+pub fn line_of_sight_3d(receiver: Vec3, satellite: Vec3, centre: Vec3, radius: f32) -> bool {
+    let seg = satellite - receiver;
+    let len2 = seg.length_squared();
+    if len2 < f32::EPSILON {
+        return true;
+    }
+    let t = ((centre - receiver).dot(seg) / len2).clamp(0., 1.);
+    let closest = receiver + seg * t;
+    // leave some room so a receiver on the surface is not blocked by rounding
+    closest.distance(centre) >= radius * (1. - 1e-4)
+}
+
+/// Angle of the satellite above the receiver's horizon, negative below it
+pub fn elevation(receiver: Vec2, satellite: Vec2, up: Vec2) -> f32 {
+    let dir = (satellite - receiver).normalize_or_zero();
+    dir.dot(up).clamp(-1., 1.).asin()
+}
+
+/// How much longer the path through the ionosphere is than straight `up`, which has to be a unit vector.
+/// Thin shell mapping function, shell at ~350km over a 6371km earth
+pub fn ionosphere_obliquity(satellite: Vec2, receiver: Vec2, up: Vec2) -> f32 {
+    let dir = (satellite - receiver).normalize_or_zero();
+    // the cosine of the elevation is the part across the local up
+    let cos_elevation = dir.perp_dot(up).abs();
+    1. / (1. - (0.948 * cos_elevation).powi(2)).sqrt()
+}
+
+/// Samples the hyperbola branch with foci `p1` and `p2` where the range difference stays `r1 - r2`.
+/// `extent` is how far from the axis between the foci the branch reaches, and `samples` how many points are used.
+/// `None` when no point can have that range difference, ie. the ranges shrunk or grown past each other by noise
+/// or satellites on top of each other. Ranges below zero are fine, only their difference counts.
+pub fn range_difference_hyperbola(
+    p1: Vec2,
+    r1: f32,
+    p2: Vec2,
+    r2: f32,
+    extent: f32,
+    samples: usize,
+) -> Option<Vec<Vec2>> {
+    let d = p1.distance(p2);
+    let focus = d * 0.5;
+    // signed distance from the centre to the vertex, along the axis from p1 to p2
+    let a = (r1 - r2) * 0.5;
+    if d < f32::EPSILON || a.abs() > focus {
+        return None;
+    }
+    let centre = (p1 + p2) * 0.5;
+    let axis = (p2 - p1) / d;
+    let normal = axis.perp();
+    let b = (focus.powi(2) - a.powi(2)).sqrt();
+    if b < 1e-3 * focus {
+        // flattened into the ray leaving the focus behind the vertex
+        let vertex = centre + axis * a;
+        return Some(vec![vertex, vertex + axis * a.signum() * extent]);
+    }
+    let t_max = (extent / b).asinh();
+    let samples = samples.max(2);
+    Some(
+        (0..samples)
+            .map(|i| {
+                let t = -t_max + 2. * t_max * i as f32 / (samples - 1) as f32;
+                centre + axis * a * t.cosh() + normal * b * t.sinh()
+            })
+            .collect(),
+    )
+}
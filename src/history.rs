@@ -0,0 +1,357 @@
+use bevy::{ecs::query::QueryData, prelude::*};
+
+use crate::{
+    input::{Action, InputMap},
+    moveable::{Deletable, DeleteMoveable, MoveableDropped, MoveableSet},
+    orbit::Orbit,
+    receiver::{spawn_receiver, ClockOffset, ReceiverColor},
+    satellite::{spawn_satellite, RangeError, Satellite},
+    Settings,
+};
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .add_event::<Spawned>()
+            .add_event::<HistoryControl>()
+            .add_event::<MoveableDropped>()
+            .add_event::<DeleteMoveable>()
+            .add_systems(
+                Update,
+                (history_keys, record_edits, apply_history)
+                    .chain()
+                    .after(MoveableSet),
+            );
+    }
+}
+
+/// Sent by whatever adds an entity the user can undo, eg. the add satellite button
+#[derive(Event)]
+pub struct Spawned(pub Entity);
+
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HistoryControl {
+    Undo,
+    Redo,
+    /// Forgets every edit, sent when a scenario or log replaces the scene
+    Clear,
+}
+
+/// What is needed to bring back a removed entity
+#[derive(Clone)]
+pub enum Snapshot {
+    Satellite {
+        position: Vec2,
+        orbit: Option<Orbit>,
+        error: RangeError,
+    },
+    Receiver {
+        position: Vec2,
+        clock_offset: f32,
+        color: Color,
+    },
+}
+
+/// One step that can be undone
+#[derive(Clone)]
+pub enum Edit {
+    /// The snapshot is taken when the spawn is undone
+    Spawn {
+        entity: Entity,
+        snapshot: Option<Snapshot>,
+    },
+    Delete {
+        entity: Entity,
+        snapshot: Snapshot,
+    },
+    Move {
+        entity: Entity,
+        from: Vec2,
+        to: Vec2,
+    },
+    Settings {
+        before: Box<Settings>,
+        after: Box<Settings>,
+    },
+}
+
+impl Edit {
+    fn entity_mut(&mut self) -> Option<&mut Entity> {
+        match self {
+            Edit::Spawn { entity, .. }
+            | Edit::Delete { entity, .. }
+            | Edit::Move { entity, .. } => Some(entity),
+            Edit::Settings { .. } => None,
+        }
+    }
+}
+
+/// Edits that can be undone and the undone ones that can be redone
+#[derive(Resource)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// Oldest edits are dropped past this
+    pub limit: usize,
+    /// Settings edits closer together than this in seconds are undone as one, eg. holding the offset buttons
+    pub merge_interval: f32,
+    /// Settings as of the last recorded or undone edit
+    settings: Option<Settings>,
+    last_settings_edit: f32,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit: 200,
+            merge_interval: 0.5,
+            settings: None,
+            last_settings_edit: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl History {
+    /// Adds a new edit, which makes the undone ones impossible to redo
+    pub fn push(&mut self, edit: Edit) {
+        self.redo.clear();
+        self.undo.push(edit);
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Drops every edit, the current settings become the new starting point
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.settings = None;
+    }
+
+    /// Points every edit about `old` to `new`, after an entity is brought back as a new one
+    pub fn remap(&mut self, old: Entity, new: Entity) {
+        for edit in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            if let Some(entity) = edit.entity_mut().filter(|e| **e == old) {
+                *entity = new;
+            }
+        }
+    }
+
+    /// Records a change to the settings, merged with the previous one if it was just before
+    fn push_settings(&mut self, settings: &Settings, time: f32) {
+        let Some(before) = self.settings.replace(settings.clone()) else {
+            return;
+        };
+        let merge = self.redo.is_empty() && time - self.last_settings_edit < self.merge_interval;
+        self.last_settings_edit = time;
+        if merge {
+            if let Some(Edit::Settings { after, .. }) = self.undo.last_mut() {
+                **after = settings.clone();
+                return;
+            }
+        }
+        self.push(Edit::Settings {
+            before: Box::new(before),
+            after: Box::new(settings.clone()),
+        });
+    }
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct Restorable {
+    transform: &'static mut Transform,
+    orbit: Option<&'static mut Orbit>,
+    error: Option<&'static RangeError>,
+    clock_offset: Option<&'static ClockOffset>,
+    color: Option<&'static ReceiverColor>,
+    satellite: Has<Satellite>,
+}
+
+fn snapshot(item: RestorableReadOnlyItem) -> Option<Snapshot> {
+    let position = item.transform.translation.xy();
+    if item.satellite {
+        Some(Snapshot::Satellite {
+            position,
+            orbit: item.orbit.cloned(),
+            error: item.error.cloned().unwrap_or_default(),
+        })
+    } else {
+        item.color.map(|color| Snapshot::Receiver {
+            position,
+            clock_offset: item.clock_offset.map_or(0., |o| o.0),
+            color: color.0,
+        })
+    }
+}
+
+fn restore(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    snapshot: &Snapshot,
+) -> Entity {
+    match snapshot {
+        Snapshot::Satellite {
+            position,
+            orbit,
+            error,
+        } => {
+            let entity = spawn_satellite(commands, asset_server, *position, settings.earth_centre);
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.insert(error.clone());
+            match orbit {
+                Some(orbit) => entity_commands.insert(orbit.clone()),
+                None => entity_commands.remove::<Orbit>(),
+            };
+            entity
+        }
+        Snapshot::Receiver {
+            position,
+            clock_offset,
+            color,
+        } => {
+            let entity = spawn_receiver(commands, asset_server, *position, *clock_offset, *color);
+            commands.entity(entity).insert(Deletable);
+            entity
+        }
+    }
+}
+
+fn history_keys(
+    key: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut control_evw: EventWriter<HistoryControl>,
+) {
+    if input_map.just_pressed(&key, Action::Undo) {
+        control_evw.send(HistoryControl::Undo);
+    }
+    if input_map.just_pressed(&key, Action::Redo) {
+        control_evw.send(HistoryControl::Redo);
+    }
+}
+
+fn record_edits(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut history: ResMut<History>,
+    mut spawned_evr: EventReader<Spawned>,
+    mut dropped_evr: EventReader<MoveableDropped>,
+    mut delete_evr: EventReader<DeleteMoveable>,
+    query: Query<Restorable>,
+) {
+    for Spawned(entity) in spawned_evr.read() {
+        history.push(Edit::Spawn {
+            entity: *entity,
+            snapshot: None,
+        });
+    }
+    for dropped in dropped_evr.read() {
+        history.push(Edit::Move {
+            entity: dropped.entity,
+            from: dropped.from,
+            to: dropped.to,
+        });
+    }
+    // the entity is only despawned at the end of the frame, so it can still be looked at
+    for DeleteMoveable(entity) in delete_evr.read() {
+        if let Some(snapshot) = query.get(*entity).ok().and_then(snapshot) {
+            history.push(Edit::Delete {
+                entity: *entity,
+                snapshot,
+            });
+        }
+    }
+    if history.settings.as_ref() != Some(&*settings) {
+        history.push_settings(&settings, time.elapsed_seconds());
+    }
+}
+
+fn apply_history(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut control_evr: EventReader<HistoryControl>,
+    mut history: ResMut<History>,
+    mut settings: ResMut<Settings>,
+    mut query: Query<Restorable>,
+) {
+    for control in control_evr.read() {
+        let undo = match control {
+            HistoryControl::Undo => true,
+            HistoryControl::Redo => false,
+            HistoryControl::Clear => {
+                history.clear();
+                continue;
+            }
+        };
+        let popped = if undo {
+            history.undo.pop()
+        } else {
+            history.redo.pop()
+        };
+        let Some(mut edit) = popped else {
+            continue;
+        };
+        match &mut edit {
+            Edit::Spawn { entity, snapshot } if undo => {
+                *snapshot = take(&mut commands, &query, *entity).or(snapshot.take());
+            }
+            Edit::Delete { entity, snapshot } if !undo => {
+                if let Some(taken) = take(&mut commands, &query, *entity) {
+                    *snapshot = taken;
+                }
+            }
+            Edit::Spawn {
+                entity,
+                snapshot: Some(snapshot),
+            }
+            | Edit::Delete { entity, snapshot } => {
+                let new = restore(&mut commands, &asset_server, &settings, snapshot);
+                history.remap(*entity, new);
+                *entity = new;
+            }
+            Edit::Spawn { snapshot: None, .. } => (),
+            Edit::Move { entity, from, to } => {
+                let to = if undo { *from } else { *to };
+                if let Ok(mut item) = query.get_mut(*entity) {
+                    item.transform.translation = to.extend(item.transform.translation.z);
+                    // otherwise the orbit puts it straight back
+                    if let Some(mut orbit) = item.orbit {
+                        orbit.set_offset(to - settings.earth_centre);
+                    }
+                }
+            }
+            Edit::Settings { before, after } => {
+                let to = if undo { before } else { after };
+                *settings = (**to).clone();
+                history.settings = Some((**to).clone());
+            }
+        }
+        if undo {
+            history.redo.push(edit);
+        } else {
+            history.undo.push(edit);
+        }
+    }
+}
+
+/// Snapshots the entity and despawns it
+fn take(commands: &mut Commands, query: &Query<Restorable>, entity: Entity) -> Option<Snapshot> {
+    let taken = query.get(entity).ok().and_then(snapshot);
+    if let Some(entity) = commands.get_entity(entity) {
+        entity.despawn_recursive();
+    }
+    taken
+}
@@ -0,0 +1,348 @@
+use std::{collections::HashMap, fmt};
+
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{
+    de::{self, EnumAccess, IntoDeserializer, VariantAccess},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<InputMap>()
+            .register_asset_loader(InputMapLoader)
+            .init_resource::<InputMap>()
+            .add_systems(Startup, load_input_map)
+            .add_systems(PreUpdate, apply_input_map);
+    }
+}
+
+/// Key bindings read at startup, relative to `assets/`, actions left out keep their default keys
+pub const INPUT_MAP: &str = "input.keys.ron";
+
+/// Things that can be done from the keyboard as well as from the ui buttons
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    AddSatellite,
+    AddReceiver,
+    ToggleCharacter,
+    ToggleRanges,
+    CycleGraphs,
+    ToggleScatter,
+    ToggleSolveClock,
+    ToggleOrbits,
+    ToggleEarth,
+    ToggleDgps,
+    Toggle3d,
+    IncreaseOffset,
+    DecreaseOffset,
+    ResetOffset,
+    IncreaseMask,
+    DecreaseMask,
+    ResetMask,
+    TogglePause,
+    Step,
+    Slower,
+    Faster,
+    ResetSpeed,
+    RecordTrack,
+    StopTrack,
+    ReplayTrack,
+    SaveScenario,
+    LoadScenario,
+    Undo,
+    Redo,
+}
+
+/// A key and the modifiers that have to be held with it, no more and no less
+///
+/// Written as the bare key name, or wrapped as `Ctrl(KeyS)`, `Shift(KeyN)` or `CtrlShift(KeyZ)`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl KeyBinding {
+    pub const fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    pub const fn ctrl(key: KeyCode) -> Self {
+        Self {
+            ctrl: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub const fn shift(key: KeyCode) -> Self {
+        Self {
+            shift: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub const fn ctrl_shift(key: KeyCode) -> Self {
+        Self {
+            ctrl: true,
+            shift: true,
+            ..Self::new(key)
+        }
+    }
+
+    fn modifiers_held(&self, input: &ButtonInput<KeyCode>) -> bool {
+        let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+        let shift = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        self.ctrl == ctrl && self.shift == shift
+    }
+}
+
+impl From<KeyCode> for KeyBinding {
+    fn from(key: KeyCode) -> Self {
+        Self::new(key)
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (index, name) = match (self.ctrl, self.shift) {
+            (false, false) => return self.key.serialize(serializer),
+            (true, false) => (0, "Ctrl"),
+            (false, true) => (1, "Shift"),
+            (true, true) => (2, "CtrlShift"),
+        };
+        serializer.serialize_newtype_variant("KeyBinding", index, name, &self.key)
+    }
+}
+
+// by hand since ron can't tell a bare key from a wrapped one through `#[serde(untagged)]`
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BindingVisitor;
+
+        impl<'de> de::Visitor<'de> for BindingVisitor {
+            type Value = KeyBinding;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f,
+                    "a key name, or one in Ctrl(..), Shift(..) or CtrlShift(..)"
+                )
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<KeyBinding, A::Error> {
+                let (VariantName(name), variant) = data.variant()?;
+                let binding: fn(KeyCode) -> KeyBinding = match name.as_str() {
+                    "Ctrl" => KeyBinding::ctrl,
+                    "Shift" => KeyBinding::shift,
+                    "CtrlShift" => KeyBinding::ctrl_shift,
+                    _ => {
+                        variant.unit_variant()?;
+                        let key = KeyCode::deserialize(name.as_str().into_deserializer())?;
+                        return Ok(KeyBinding::new(key));
+                    }
+                };
+                Ok(binding(variant.newtype_variant()?))
+            }
+        }
+
+        deserializer.deserialize_enum("KeyBinding", &[], BindingVisitor)
+    }
+}
+
+struct VariantName(String);
+
+impl<'de> Deserialize<'de> for VariantName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NameVisitor;
+
+        impl<'de> de::Visitor<'de> for NameVisitor {
+            type Value = VariantName;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a key name")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<VariantName, E> {
+                Ok(VariantName(name.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(NameVisitor)
+    }
+}
+
+/// Keys bound to each action, any of them triggers it
+#[derive(Resource, Asset, TypePath, Clone, Serialize, Deserialize)]
+pub struct InputMap {
+    pub bindings: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use KeyCode::*;
+        let key = KeyBinding::new;
+        Self {
+            bindings: HashMap::from([
+                (Action::AddSatellite, vec![key(KeyN)]),
+                (Action::AddReceiver, vec![KeyBinding::shift(KeyN)]),
+                (Action::ToggleCharacter, vec![key(KeyC)]),
+                (Action::ToggleRanges, vec![key(KeyR)]),
+                (Action::CycleGraphs, vec![key(KeyG)]),
+                (Action::ToggleScatter, vec![key(KeyX)]),
+                (Action::ToggleSolveClock, vec![key(KeyB)]),
+                (Action::ToggleOrbits, vec![key(KeyO)]),
+                (Action::ToggleEarth, vec![key(KeyE)]),
+                (Action::ToggleDgps, vec![key(KeyD)]),
+                (Action::Toggle3d, vec![key(KeyV)]),
+                (Action::IncreaseOffset, vec![key(Equal), key(NumpadAdd)]),
+                (
+                    Action::DecreaseOffset,
+                    vec![key(Minus), key(NumpadSubtract)],
+                ),
+                (Action::ResetOffset, vec![key(Digit0), key(Numpad0)]),
+                (Action::IncreaseMask, vec![key(PageUp)]),
+                (Action::DecreaseMask, vec![key(PageDown)]),
+                (Action::ResetMask, vec![key(Home)]),
+                (Action::TogglePause, vec![key(Space)]),
+                (Action::Step, vec![key(Period)]),
+                (Action::Slower, vec![key(BracketLeft)]),
+                (Action::Faster, vec![key(BracketRight)]),
+                (Action::ResetSpeed, vec![key(Backslash)]),
+                (Action::RecordTrack, vec![key(KeyT)]),
+                (Action::StopTrack, vec![KeyBinding::shift(KeyT)]),
+                (Action::ReplayTrack, vec![key(KeyP)]),
+                (Action::SaveScenario, vec![KeyBinding::ctrl(KeyS)]),
+                (Action::LoadScenario, vec![KeyBinding::ctrl(KeyL)]),
+                (Action::Undo, vec![KeyBinding::ctrl(KeyZ)]),
+                (
+                    Action::Redo,
+                    vec![KeyBinding::ctrl_shift(KeyZ), KeyBinding::ctrl(KeyY)],
+                ),
+            ]),
+        }
+    }
+}
+
+impl InputMap {
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .get(&action)
+            .map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// Replaces the keys of `action`, an empty list unbinds it
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings.insert(action, keys);
+    }
+
+    /// Rebinds every action that `other` has keys for
+    pub fn merge(&mut self, other: &InputMap) {
+        for (action, keys) in other.bindings.iter() {
+            self.bind(*action, keys.clone());
+        }
+    }
+
+    /// Action whose keys are held along with exactly their modifiers
+    pub fn pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        self.keys(action)
+            .iter()
+            .any(|binding| binding.modifiers_held(input) && input.pressed(binding.key))
+    }
+
+    /// Action whose keys went down this frame
+    pub fn just_pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        self.keys(action)
+            .iter()
+            .any(|binding| binding.modifiers_held(input) && input.just_pressed(binding.key))
+    }
+}
+
+#[derive(Default)]
+struct InputMapLoader;
+
+#[derive(Debug)]
+pub enum InputMapLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for InputMapLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMapLoaderError::Io(err) => write!(f, "could not read key bindings: {}", err),
+            InputMapLoaderError::Ron(err) => write!(f, "could not parse key bindings: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InputMapLoaderError {}
+
+impl AssetLoader for InputMapLoader {
+    type Asset = InputMap;
+    type Settings = ();
+    type Error = InputMapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<InputMap, InputMapLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(InputMapLoaderError::Io)?;
+            ron::de::from_bytes(&bytes).map_err(InputMapLoaderError::Ron)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["keys.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct InputMapHandle(Handle<InputMap>);
+
+fn load_input_map(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(InputMapHandle(asset_server.load(INPUT_MAP)));
+}
+
+/// Puts the bindings from the config file on top of the defaults, again whenever the file changes
+fn apply_input_map(
+    handle: Option<Res<InputMapHandle>>,
+    maps: Res<Assets<InputMap>>,
+    mut input_map: ResMut<InputMap>,
+    mut asset_evr: EventReader<AssetEvent<InputMap>>,
+) {
+    let Some(handle) = handle else {
+        asset_evr.clear();
+        return;
+    };
+    for ev in asset_evr.read() {
+        if !matches!(ev, AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+            if *id == handle.0.id())
+        {
+            continue;
+        }
+        if let Some(loaded) = maps.get(&handle.0) {
+            let mut map = InputMap::default();
+            map.merge(loaded);
+            *input_map = map;
+            info!("loaded key bindings from {}", INPUT_MAP);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{math::vec2, prelude::*};
+use clock::ClockPlugin;
+use dgps::DgpsPlugin;
+use earth::EarthPlugin;
+use geo::GeoReference;
+use gps::{elevation, line_of_sight, line_of_sight_3d};
+use orbit::OrbitPlugin;
+use receiver::ClockOffset;
+use satellite::SatellitePlugin;
+use scatter::ScatterPlugin;
+use serde::{Deserialize, Serialize};
+use solver::SolverPlugin;
+use track::TrackPlugin;
+
+pub mod background;
+pub mod camera;
+pub mod clock;
+pub mod dgps;
+pub mod draw;
+pub mod earth;
+pub mod floppy;
+pub mod geo;
+pub mod gps;
+pub mod history;
+pub mod input;
+pub mod moveable;
+pub mod nmea;
+pub mod orbit;
+pub mod placement;
+pub mod receiver;
+pub mod satellite;
+pub mod scatter;
+pub mod scenario;
+pub mod solver;
+pub mod track;
+pub mod ui;
+pub mod view3d;
+
+/// Scene logic that does not need a window or renderer
+pub struct SimulationPlugin;
+/// Systems that update the simulation state from the satellite ranges
+#[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
+pub struct SimulationSet;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .init_resource::<GeoReference>()
+            .add_plugins((
+                ClockPlugin,
+                SatellitePlugin,
+                EarthPlugin,
+                OrbitPlugin,
+                SolverPlugin,
+                ScatterPlugin,
+                DgpsPlugin,
+                TrackPlugin,
+            ));
+    }
+}
+
+/// App running only the simulation, for tests and machines without a GPU
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, SimulationPlugin));
+    app
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum GraphVisibility {
+    None,
+    Some,
+    All,
+}
+
+#[derive(Resource, Clone, PartialEq)]
+pub struct Settings {
+    pub character_visible: bool,
+    pub ranges_visible: bool,
+    pub graph_visibility: GraphVisibility,
+    /// Clock offset shared by every receiver, on top of their own `ClockOffset`
+    pub ranges_offset: f32,
+    /// Let the solver recover the clock offsets instead of absorbing them into the position
+    pub solve_clock_bias: bool,
+    /// Show a cloud of fixes from repeatedly sampled noisy ranges
+    pub scatter_visible: bool,
+    /// How far the range difference hyperbolas reach from the satellites
+    pub hyperbola_extent: f32,
+    /// Points used to draw each hyperbola
+    pub hyperbola_samples: usize,
+    /// Centre of the earth the satellites orbit
+    pub earth_centre: Vec2,
+    /// Advance the satellites along their orbits
+    pub orbits_moving: bool,
+    /// Stand the receivers on the earth and leave out satellites below their horizon
+    pub earth_enabled: bool,
+    pub earth_radius: f32,
+    /// Lowest elevation in radians a satellite can have and still be used, -90 degrees turns it off
+    pub elevation_mask: f32,
+    /// Correct the receivers with the ranges measured at the base station
+    pub dgps_enabled: bool,
+    /// Show the 3D view instead of the 2D one
+    pub view_3d: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            character_visible: true,
+            ranges_visible: true,
+            graph_visibility: GraphVisibility::All,
+            ranges_offset: 0.,
+            solve_clock_bias: false,
+            scatter_visible: false,
+            hyperbola_extent: 20000.,
+            hyperbola_samples: 200,
+            earth_centre: vec2(0., -6000.),
+            orbits_moving: false,
+            earth_enabled: false,
+            earth_radius: 6000.,
+            elevation_mask: -FRAC_PI_2,
+            dgps_enabled: false,
+            view_3d: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Total clock offset of a receiver in world units
+    pub fn clock_offset(&self, receiver: Option<&ClockOffset>) -> f32 {
+        self.ranges_offset + receiver.map_or(0., |o| o.0)
+    }
+
+    /// Straight up from the earth under the receiver, or +y without the earth
+    pub fn local_up(&self, receiver: Vec2) -> Vec2 {
+        if self.earth_enabled {
+            (receiver - self.earth_centre)
+                .try_normalize()
+                .unwrap_or(Vec2::Y)
+        } else {
+            Vec2::Y
+        }
+    }
+
+    /// Whether a receiver can see the satellite and it is above the elevation mask
+    pub fn in_view(&self, receiver: Vec2, satellite: Vec2) -> bool {
+        if self.earth_enabled
+            && !line_of_sight(receiver, satellite, self.earth_centre, self.earth_radius)
+        {
+            return false;
+        }
+        self.elevation_mask <= -FRAC_PI_2
+            || elevation(receiver, satellite, self.local_up(receiver)) >= self.elevation_mask
+    }
+
+    /// [`Settings::in_view`] for the 3D view, where the receiver is in the plane z = 0
+    pub fn in_view_3d(&self, receiver: Vec3, satellite: Vec3) -> bool {
+        if self.earth_enabled
+            && !line_of_sight_3d(
+                receiver,
+                satellite,
+                self.earth_centre.extend(0.),
+                self.earth_radius,
+            )
+        {
+            return false;
+        }
+        let up = self.local_up(receiver.xy()).extend(0.);
+        let dir = (satellite - receiver).normalize_or_zero();
+        self.elevation_mask <= -FRAC_PI_2
+            || dir.dot(up).clamp(-1., 1.).asin() >= self.elevation_mask
+    }
+}
+
+#[derive(Component)]
+pub struct Character;
+
+/// Writes `text` to `path` relative to `assets/`, creating the folders on the way
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_asset_file(path: &str, text: &str) -> Result<(), String> {
+    let path = std::path::Path::new("assets").join(path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(path, text).map_err(|err| err.to_string())
+}
+
+/// The web build has no file system to save to
+#[cfg(target_arch = "wasm32")]
+pub fn write_asset_file(_path: &str, _text: &str) -> Result<(), String> {
+    Err("saving files is not supported in the web build".to_string())
+}
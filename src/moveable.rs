@@ -1,16 +1,24 @@
-use bevy::{math::vec2, prelude::*, window::PrimaryWindow};
+use bevy::{ecs::system::SystemParam, prelude::*};
 
-use crate::camera::CameraController;
+use crate::camera::{WorldCursor, WorldCursorSet};
 
 pub struct MoveablePlugin;
+#[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
+pub struct MoveableSet;
 
 impl Plugin for MoveablePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CurMoving {
             entity: None,
             offset: Vec2::ZERO,
+            start: Vec2::ZERO,
         });
-        app.add_systems(Update, update_moveables);
+        app.add_event::<MoveableDropped>()
+            .add_event::<DeleteMoveable>();
+        app.configure_sets(Update, MoveableSet.after(WorldCursorSet))
+            .add_systems(Update, update_moveables.in_set(MoveableSet));
+        // late, so everything else can still look at the entity in the frame it is deleted
+        app.add_systems(PostUpdate, delete_moveables);
     }
 }
 
@@ -24,40 +32,57 @@ pub struct Moveable {
 #[derive(Component)]
 pub struct Deletable;
 
+/// The entity being dragged, if any
 #[derive(Resource)]
-struct CurMoving {
-    entity: Option<Entity>,
+pub struct CurMoving {
+    pub entity: Option<Entity>,
     offset: Vec2,
+    /// Where the drag started
+    start: Vec2,
+}
+
+/// Sent when a drag ends, with where it started and ended
+#[derive(Event)]
+pub struct MoveableDropped {
+    pub entity: Entity,
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+/// Request to remove a `Deletable`, it is despawned at the end of the frame
+#[derive(Event)]
+pub struct DeleteMoveable(pub Entity);
+
+#[derive(SystemParam)]
+struct MoveableEvents<'w> {
+    dropped: EventWriter<'w, MoveableDropped>,
+    delete: EventWriter<'w, DeleteMoveable>,
 }
 
 fn update_moveables(
     time: Res<Time>,
-    mut commands: Commands,
+    mut events: MoveableEvents,
     mut cur_moving: ResMut<CurMoving>,
-    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    mut window_query: Query<&mut Window>,
     mouse: Res<ButtonInput<MouseButton>>,
-    camera: Query<&Transform, With<CameraController>>,
-    mut moveable_query: Query<
-        (
-            &mut Transform,
-            &mut Moveable,
-            Entity,
-            Has<Deletable>,
-            &InheritedVisibility,
-        ),
-        Without<CameraController>,
-    >,
+    cursor: Res<WorldCursor>,
+    mut moveable_query: Query<(
+        &mut Transform,
+        &mut Moveable,
+        Entity,
+        Has<Deletable>,
+        &InheritedVisibility,
+    )>,
 ) {
     // Amazing example of good code and SRP...
-    let mut window = window.single_mut();
-    let camera_transform = camera.single();
-    let mouse_pos = (match window.cursor_position() {
-        Some(v) => v,
-        None => return,
-    } - vec2(window.width(), window.height()) / 2.)
-        * camera_transform.scale.xy()
-        * vec2(1., -1.)
-        + camera_transform.translation.xy();
+    let (Some(mouse_pos), Some(window)) = (cursor.position, cursor.window) else {
+        return;
+    };
+    let Ok(mut window) = window_query.get_mut(window) else {
+        return;
+    };
+    // the selection radius is in pixels
+    let scale = cursor.units_per_pixel;
     window.cursor.icon = CursorIcon::Default;
 
     if let Some(moving) = cur_moving.entity {
@@ -69,16 +94,24 @@ fn update_moveables(
         if !mouse.pressed(MouseButton::Left) {
             cur_moving.entity = None;
             moveable.velocity = Vec2::ZERO;
+            let to = moveable_transform.translation.xy();
+            if to != cur_moving.start {
+                events.dropped.send(MoveableDropped {
+                    entity: moving,
+                    from: cur_moving.start,
+                    to,
+                });
+            }
             return;
         }
-        let new_pos = mouse_pos + cur_moving.offset * camera_transform.scale.xy();
+        let new_pos = mouse_pos + cur_moving.offset * scale;
         moveable.velocity = (new_pos - moveable_transform.translation.xy()) / time.delta_seconds();
         moveable_transform.translation = new_pos.extend(moveable_transform.translation.z);
         return;
     }
 
     for (transform, moveable, entity, has_deleteable, vis) in moveable_query.iter_mut() {
-        let offset = (transform.translation.xy() - mouse_pos) / camera_transform.scale.xy();
+        let offset = (transform.translation.xy() - mouse_pos) / scale;
         if !vis.get() {
             continue;
         }
@@ -87,10 +120,19 @@ fn update_moveables(
             if mouse.just_pressed(MouseButton::Left) {
                 cur_moving.offset = offset;
                 cur_moving.entity = Some(entity);
+                cur_moving.start = transform.translation.xy();
                 return;
             } else if mouse.just_pressed(MouseButton::Right) && has_deleteable {
-                commands.entity(entity).despawn_recursive();
+                events.delete.send(DeleteMoveable(entity));
             }
         }
     }
 }
+
+fn delete_moveables(mut commands: Commands, mut delete_evr: EventReader<DeleteMoveable>) {
+    for DeleteMoveable(entity) in delete_evr.read() {
+        if let Some(entity) = commands.get_entity(*entity) {
+            entity.despawn_recursive();
+        }
+    }
+}
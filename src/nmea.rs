@@ -0,0 +1,451 @@
+//! Reading NMEA 0183 logs, so recorded receiver output can drive the character and satellites
+
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+
+use crate::{
+    clock::SimClock,
+    geo::{nmea_checksum, GeoReference},
+    history::HistoryControl,
+    moveable::Deletable,
+    satellite::Satellite,
+    Character, Settings,
+};
+
+pub struct NmeaPlugin;
+
+impl Plugin for NmeaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<NmeaLog>()
+            .register_asset_loader(NmeaLoader)
+            .add_event::<LoadNmea>()
+            .add_event::<HistoryControl>()
+            .init_resource::<NmeaPlayback>()
+            .add_systems(
+                Update,
+                (start_loading_log, start_playback, play_log).chain(),
+            );
+    }
+}
+
+/// Log played by the log button, relative to `assets/`
+pub const NMEA_LOG: &str = "logs/sample.nmea";
+
+/// How far from the receiver the logged satellites are drawn
+pub const LOG_SKY_RADIUS: f32 = 3000.;
+
+/// Satellite as reported by a GSV sentence
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SatelliteView {
+    pub prn: u32,
+    /// Degrees above the horizon
+    pub elevation: Option<f32>,
+    /// Degrees clockwise from north
+    pub azimuth: Option<f32>,
+    pub snr: Option<f32>,
+}
+
+/// The sentences that are understood, any talker id is accepted
+#[derive(Clone, PartialEq, Debug)]
+pub enum Sentence {
+    Gga {
+        /// Seconds since midnight UTC
+        time: f64,
+        /// Latitude and longitude in degrees, `None` without a fix
+        position: Option<(f64, f64)>,
+        satellites: Option<u32>,
+        hdop: Option<f32>,
+    },
+    Rmc {
+        time: f64,
+        position: Option<(f64, f64)>,
+    },
+    Gsv {
+        messages: u32,
+        number: u32,
+        satellites: Vec<SatelliteView>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NmeaError {
+    /// The checksum after `*` does not match the sentence
+    Checksum,
+    Malformed,
+    /// A valid sentence of a type that is not read, eg. GSA or VTG
+    Unsupported(String),
+}
+
+impl fmt::Display for NmeaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NmeaError::Checksum => write!(f, "checksum does not match"),
+            NmeaError::Malformed => write!(f, "malformed sentence"),
+            NmeaError::Unsupported(kind) => write!(f, "unsupported sentence {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for NmeaError {}
+
+pub fn parse_sentence(line: &str) -> Result<Sentence, NmeaError> {
+    let line = line.trim().strip_prefix('$').ok_or(NmeaError::Malformed)?;
+    let body = match line.split_once('*') {
+        Some((body, checksum)) => {
+            let checksum = u8::from_str_radix(checksum, 16).map_err(|_| NmeaError::Malformed)?;
+            if checksum != nmea_checksum(body) {
+                return Err(NmeaError::Checksum);
+            }
+            body
+        }
+        None => line,
+    };
+    let fields: Vec<_> = body.split(',').collect();
+    let field = |i: usize| fields.get(i).copied().unwrap_or("");
+    let kind = field(0);
+    // a corrupt byte comes through the lossy decoding as a multibyte char, so check before slicing
+    if kind.len() != 5 || !kind.is_ascii() {
+        return Err(NmeaError::Malformed);
+    }
+    match &kind[2..] {
+        "GGA" => Ok(Sentence::Gga {
+            time: parse_time(field(1))?,
+            position: match field(6) {
+                "" | "0" => None,
+                _ => fields.get(2..6).and_then(parse_position),
+            },
+            satellites: field(7).parse().ok(),
+            hdop: field(8).parse().ok(),
+        }),
+        "RMC" => Ok(Sentence::Rmc {
+            time: parse_time(field(1))?,
+            position: match field(2) {
+                "A" => fields.get(3..7).and_then(parse_position),
+                _ => None,
+            },
+        }),
+        "GSV" => Ok(Sentence::Gsv {
+            messages: field(1).parse().map_err(|_| NmeaError::Malformed)?,
+            number: field(2).parse().map_err(|_| NmeaError::Malformed)?,
+            // newer receivers can end the sentence with a signal id, which is not a whole group
+            satellites: fields
+                .get(4..)
+                .unwrap_or_default()
+                .chunks_exact(4)
+                .filter_map(|group| {
+                    Some(SatelliteView {
+                        prn: group[0].parse().ok()?,
+                        elevation: group[1].parse().ok(),
+                        azimuth: group[2].parse().ok(),
+                        snr: group[3].parse().ok(),
+                    })
+                })
+                .collect(),
+        }),
+        other => Err(NmeaError::Unsupported(other.to_string())),
+    }
+}
+
+/// hhmmss.ss to seconds since midnight
+fn parse_time(field: &str) -> Result<f64, NmeaError> {
+    // lines without a checksum are not validated, so a corrupt byte can still be in here
+    if field.len() < 6 || !field.is_ascii() {
+        return Err(NmeaError::Malformed);
+    }
+    let parse = |s: &str| s.parse::<f64>().map_err(|_| NmeaError::Malformed);
+    Ok(parse(&field[0..2])? * 3600. + parse(&field[2..4])? * 60. + parse(&field[4..])?)
+}
+
+/// Degrees from the four fields ddmm.mm,N,dddmm.mm,E
+fn parse_position(fields: &[&str]) -> Option<(f64, f64)> {
+    let [lat, ns, lon, ew] = fields else {
+        return None;
+    };
+    let degrees = |value: &str| {
+        let value = value.parse::<f64>().ok()?;
+        let whole = (value / 100.).floor();
+        Some(whole + (value - whole * 100.) / 60.)
+    };
+    let lat = degrees(lat)? * if *ns == "S" { -1. } else { 1. };
+    let lon = degrees(lon)? * if *ew == "W" { -1. } else { 1. };
+    Some((lat, lon))
+}
+
+/// Everything the log says about one moment
+#[derive(Clone, Debug)]
+pub struct NmeaEpoch {
+    /// Seconds since the first sentence of the log
+    pub time: f32,
+    pub position: Option<(f64, f64)>,
+    /// Last reported satellites in view
+    pub satellites: Vec<SatelliteView>,
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct NmeaLog {
+    pub epochs: Vec<NmeaEpoch>,
+}
+
+impl NmeaLog {
+    /// Groups the sentences by their time, lines that cannot be read are skipped
+    pub fn parse(text: &str) -> Self {
+        let mut epochs: Vec<NmeaEpoch> = Vec::new();
+        let mut first = None;
+        let mut last_time = 0.;
+        let mut days = 0.;
+        let mut in_view = Vec::new();
+        let mut pending_view = Vec::new();
+        for line in text.lines() {
+            let (time, position) = match parse_sentence(line) {
+                Ok(Sentence::Gga { time, position, .. } | Sentence::Rmc { time, position }) => {
+                    (time, position)
+                }
+                Ok(Sentence::Gsv {
+                    messages,
+                    number,
+                    satellites,
+                }) => {
+                    if number == 1 {
+                        pending_view.clear();
+                    }
+                    pending_view.extend(satellites);
+                    if number == messages {
+                        in_view = pending_view.clone();
+                        if let Some(epoch) = epochs.last_mut() {
+                            epoch.satellites = in_view.clone();
+                        }
+                    }
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            // the time of day starts over at midnight
+            if time + days < last_time - 43200. {
+                days += 86400.;
+            }
+            let time = time + days;
+            last_time = time;
+            let first = *first.get_or_insert(time);
+            let time = (time - first) as f32;
+            if epochs.last().map(|e| e.time) != Some(time) {
+                epochs.push(NmeaEpoch {
+                    time,
+                    position: None,
+                    satellites: in_view.clone(),
+                });
+            }
+            let epoch = epochs.last_mut().unwrap();
+            if position.is_some() {
+                epoch.position = position;
+            }
+        }
+        Self { epochs }
+    }
+
+    /// The last epoch at or before `time`, `None` a second after the log has ended
+    pub fn epoch_at(&self, time: f32) -> Option<&NmeaEpoch> {
+        let last = self.epochs.last()?;
+        if time > last.time + 1. {
+            return None;
+        }
+        let i = self.epochs.partition_point(|e| e.time <= time);
+        self.epochs.get(i.saturating_sub(1))
+    }
+}
+
+/// Where a satellite seen at this azimuth and elevation is drawn around the receiver
+///
+/// The map is the vertical east-west plane, so the elevation is kept as the angle above the local
+/// horizon and the azimuth only picks the east or west side of the sky.
+pub fn sky_position(receiver: Vec2, up: Vec2, azimuth: f32, elevation: f32) -> Vec2 {
+    let east = Vec2::new(up.y, -up.x);
+    let side = if azimuth.to_radians().sin() < 0. {
+        -east
+    } else {
+        east
+    };
+    let elevation = elevation.clamp(0., 90.).to_radians();
+    receiver + (side * elevation.cos() + up * elevation.sin()) * LOG_SKY_RADIUS
+}
+
+#[derive(Default)]
+struct NmeaLoader;
+
+#[derive(Debug)]
+pub enum NmeaLoaderError {
+    Io(std::io::Error),
+    Empty,
+}
+
+impl fmt::Display for NmeaLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NmeaLoaderError::Io(err) => write!(f, "could not read nmea log: {}", err),
+            NmeaLoaderError::Empty => write!(f, "no GGA or RMC sentences in nmea log"),
+        }
+    }
+}
+
+impl std::error::Error for NmeaLoaderError {}
+
+impl AssetLoader for NmeaLoader {
+    type Asset = NmeaLog;
+    type Settings = ();
+    type Error = NmeaLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<NmeaLog, NmeaLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(NmeaLoaderError::Io)?;
+            let log = NmeaLog::parse(&String::from_utf8_lossy(&bytes));
+            if log.epochs.is_empty() {
+                return Err(NmeaLoaderError::Empty);
+            }
+            Ok(log)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["nmea"]
+    }
+}
+
+/// Request to play the log at the given asset path
+#[derive(Event)]
+pub struct LoadNmea(pub String);
+
+#[derive(Resource, Default)]
+pub struct NmeaPlayback {
+    handle: Option<Handle<NmeaLog>>,
+    /// Simulated time the playback started at, `None` while nothing is playing
+    start: Option<f32>,
+    pending: bool,
+}
+
+/// Satellite placed from a log, identified by its PRN
+#[derive(Component)]
+pub struct LoggedSatellite(pub u32);
+
+fn start_loading_log(
+    asset_server: Res<AssetServer>,
+    mut load_evr: EventReader<LoadNmea>,
+    mut playback: ResMut<NmeaPlayback>,
+) {
+    for LoadNmea(path) in load_evr.read() {
+        let handle = asset_server.load::<NmeaLog>(path.clone());
+        asset_server.reload(path.clone());
+        playback.handle = Some(handle);
+        playback.start = None;
+        playback.pending = true;
+    }
+}
+
+fn start_playback(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    mut playback: ResMut<NmeaPlayback>,
+    mut asset_evr: EventReader<AssetEvent<NmeaLog>>,
+    mut history_evw: EventWriter<HistoryControl>,
+    satellite_query: Query<Entity, (With<Satellite>, With<Deletable>)>,
+) {
+    let Some(handle) = playback.handle.clone() else {
+        asset_evr.clear();
+        return;
+    };
+    // read every event so they do not pile up
+    let loaded = asset_evr
+        .read()
+        .filter(|ev| {
+            matches!(ev, AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.id())
+        })
+        .count()
+        > 0;
+    if !loaded || !playback.pending {
+        return;
+    }
+    playback.pending = false;
+    playback.start = Some(clock.elapsed_seconds());
+    history_evw.send(HistoryControl::Clear);
+    // the log brings its own satellites
+    for entity in satellite_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_log(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    clock: Res<SimClock>,
+    reference: Res<GeoReference>,
+    settings: Res<Settings>,
+    logs: Res<Assets<NmeaLog>>,
+    mut playback: ResMut<NmeaPlayback>,
+    mut character_query: Query<&mut Transform, (With<Character>, Without<LoggedSatellite>)>,
+    mut satellite_query: Query<(Entity, &LoggedSatellite, &mut Transform)>,
+) {
+    let (Some(start), Some(handle)) = (playback.start, playback.handle.as_ref()) else {
+        return;
+    };
+    let Some(log) = logs.get(handle) else {
+        return;
+    };
+    let Some(epoch) = log.epoch_at(clock.elapsed_seconds() - start) else {
+        playback.start = None;
+        return;
+    };
+    let Ok(mut character) = character_query.get_single_mut() else {
+        return;
+    };
+    // only the east-west part of the logged track is on the map, the receiver keeps its height
+    if let Some((_, lon)) = epoch.position {
+        let x = reference.east_of(lon);
+        if character.translation.x != x {
+            character.translation.x = x;
+        }
+    }
+    let receiver = character.translation.xy();
+    let up = settings.local_up(receiver);
+
+    let mut placed: HashMap<u32, Vec2> = epoch
+        .satellites
+        .iter()
+        .filter_map(|s| Some((s.prn, sky_position(receiver, up, s.azimuth?, s.elevation?))))
+        .collect();
+    for (entity, logged, mut transform) in satellite_query.iter_mut() {
+        match placed.remove(&logged.0) {
+            Some(pos) => {
+                if transform.translation.xy() != pos {
+                    transform.translation = pos.extend(transform.translation.z);
+                }
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    for (prn, pos) in placed {
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("Satellite.png"),
+                transform: Transform::from_translation(pos.extend(1.)),
+                ..default()
+            },
+            Satellite,
+            LoggedSatellite(prn),
+            Deletable,
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use std::f32::consts::TAU;
+
+use bevy::{math::vec2, prelude::*};
+
+use crate::{
+    clock::{ClockSet, SimClock},
+    moveable::{CurMoving, MoveableSet},
+    satellite::RangeSampleSet,
+    Settings, SimulationSet,
+};
+
+pub struct OrbitPlugin;
+#[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
+pub struct OrbitSet;
+
+impl Plugin for OrbitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_orbits
+                .in_set(OrbitSet)
+                .after(ClockSet)
+                .after(MoveableSet)
+                .before(RangeSampleSet)
+                .before(SimulationSet),
+        );
+    }
+}
+
+/// Keplerian orbit in the plane around `Settings::earth_centre`
+#[derive(Component, Clone)]
+pub struct Orbit {
+    /// Semi-major axis, the radius for a circular orbit
+    pub radius: f32,
+    /// 0 for a circle, must stay below 1
+    pub eccentricity: f32,
+    /// Angle of the closest point to the earth
+    pub periapsis: f32,
+    /// Seconds for one revolution
+    pub period: f32,
+    /// Mean anomaly, advances at a constant rate over the orbit
+    pub phase: f32,
+}
+
+/// Period of a circular orbit of this radius, scaled from a 60 second orbit 7000 units out
+pub fn orbit_period(radius: f32) -> f32 {
+    60. * (radius / 7000.).powf(1.5)
+}
+
+impl Orbit {
+    /// Circular orbit that passes through `position` right now
+    pub fn circular_through(centre: Vec2, position: Vec2) -> Self {
+        let offset = position - centre;
+        let radius = offset.length();
+        Self {
+            radius,
+            eccentricity: 0.,
+            periapsis: 0.,
+            period: orbit_period(radius),
+            phase: offset.y.atan2(offset.x),
+        }
+    }
+
+    /// Position relative to the centre of the orbit
+    pub fn offset(&self) -> Vec2 {
+        let e = self.eccentricity;
+        // Kepler's equation, M = E - e sin E, by Newton's method
+        let mut anomaly = self.phase;
+        for _ in 0..8 {
+            anomaly -= (anomaly - e * anomaly.sin() - self.phase) / (1. - e * anomaly.cos());
+        }
+        let true_anomaly = 2.
+            * ((1. + e).sqrt() * (anomaly * 0.5).sin())
+                .atan2((1. - e).sqrt() * (anomaly * 0.5).cos());
+        let distance = self.radius * (1. - e * anomaly.cos());
+        let angle = self.periapsis + true_anomaly;
+        vec2(angle.cos(), angle.sin()) * distance
+    }
+
+    /// Changes the size and phase of the orbit so it passes through `offset`, keeping its shape.
+    /// The period follows the new size
+    pub fn set_offset(&mut self, offset: Vec2) {
+        let e = self.eccentricity;
+        let true_anomaly = offset.y.atan2(offset.x) - self.periapsis;
+        self.set_radius(offset.length() * (1. + e * true_anomaly.cos()) / (1. - e * e));
+        let anomaly = 2.
+            * ((1. - e).sqrt() * (true_anomaly * 0.5).sin())
+                .atan2((1. + e).sqrt() * (true_anomaly * 0.5).cos());
+        self.phase = anomaly - e * anomaly.sin();
+    }
+
+    /// Resizes the orbit, with the period Kepler's third law gives for the new size
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+        self.period = orbit_period(radius);
+    }
+
+    /// Grows the orbit if its closest point would be inside a planet of `radius`
+    pub fn keep_above(&mut self, radius: f32) {
+        let min = radius / (1. - self.eccentricity);
+        if self.radius < min {
+            self.set_radius(min);
+        }
+    }
+}
+
+fn update_orbits(
+    clock: Res<SimClock>,
+    settings: Res<Settings>,
+    cur_moving: Option<Res<CurMoving>>,
+    mut query: Query<(Entity, &mut Transform, &mut Orbit)>,
+) {
+    let dragged = cur_moving.and_then(|c| c.entity);
+    // a flat scene with still satellites leaves them where they were put, even inside the earth
+    let keep_above = settings.earth_enabled || settings.orbits_moving;
+    for (entity, mut transform, mut orbit) in query.iter_mut() {
+        if dragged == Some(entity) {
+            // let the drag place the satellite and fit the orbit to it instead
+            orbit.set_offset(transform.translation.xy() - settings.earth_centre);
+            continue;
+        }
+        if keep_above {
+            orbit.keep_above(settings.earth_radius);
+        }
+        if settings.orbits_moving {
+            orbit.phase = (orbit.phase + TAU * clock.delta_seconds() / orbit.period) % TAU;
+        }
+        let pos = (settings.earth_centre + orbit.offset()).extend(transform.translation.z);
+        // only touch the transform when it moves, other systems watch it for changes
+        if transform.translation != pos {
+            transform.translation = pos;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::{WorldCursor, WorldCursorSet},
+    history::Spawned,
+    moveable::MoveableSet,
+    satellite::spawn_satellite,
+    Settings,
+};
+
+pub struct PlacementPlugin;
+
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Placement>()
+            .add_event::<Spawned>()
+            // checked before placing, so the click that places a satellite does not also start a drag
+            .configure_sets(Update, MoveableSet.run_if(not(placement_armed)))
+            .add_systems(Startup, spawn_ghost)
+            .add_systems(
+                Update,
+                place_satellites.after(MoveableSet).after(WorldCursorSet),
+            );
+    }
+}
+
+/// While armed the next click in the world places a satellite there
+#[derive(Resource, Default)]
+pub struct Placement {
+    pub armed: bool,
+}
+
+/// Preview of the satellite under the cursor while placing
+#[derive(Component)]
+struct PlacementGhost;
+
+fn placement_armed(placement: Res<Placement>) -> bool {
+    placement.armed
+}
+
+fn spawn_ghost(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load("Satellite.png"),
+            sprite: Sprite {
+                color: Color::rgba(1., 1., 1., 0.4),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., 2.),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        PlacementGhost,
+    ));
+}
+
+/// Moves the ghost with the cursor and places the satellite on a left click, esc or right click cancels
+#[allow(clippy::too_many_arguments)]
+fn place_satellites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut placement: ResMut<Placement>,
+    mut spawned_evw: EventWriter<Spawned>,
+    cursor: Res<WorldCursor>,
+    mut window_query: Query<&mut Window>,
+    interaction_query: Query<&Interaction>,
+    mut ghost_query: Query<(&mut Transform, &mut Visibility, &PlacementGhost)>,
+) {
+    let cancelled = key.just_pressed(KeyCode::Escape) || mouse.just_pressed(MouseButton::Right);
+    // placing only makes sense on the 2D map
+    if placement.armed && (cancelled || settings.view_3d) {
+        placement.armed = false;
+    }
+    let cursor = match (cursor.position, cursor.window) {
+        (Some(position), Some(window)) if placement.armed => window_query
+            .get_mut(window)
+            .ok()
+            .map(|window| (window, position)),
+        _ => None,
+    };
+    let Some((mut window, cursor)) = cursor else {
+        for (_, mut vis, _) in ghost_query.iter_mut() {
+            *vis = Visibility::Hidden;
+        }
+        return;
+    };
+    window.cursor.icon = CursorIcon::Crosshair;
+    for (mut transform, mut vis, _) in ghost_query.iter_mut() {
+        transform.translation = cursor.extend(transform.translation.z);
+        *vis = Visibility::Inherited;
+    }
+    // the press on the button that armed the tool is not a click in the world
+    let over_ui = interaction_query.iter().any(|i| *i != Interaction::None);
+    if !mouse.just_pressed(MouseButton::Left) || over_ui {
+        return;
+    }
+    spawned_evw.send(Spawned(spawn_satellite(
+        &mut commands,
+        &asset_server,
+        cursor,
+        settings.earth_centre,
+    )));
+    // shift keeps the tool armed to place several in a row
+    if !key.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        placement.armed = false;
+    }
+}
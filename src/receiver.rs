@@ -0,0 +1,142 @@
+use bevy::{
+    math::{vec2, vec3},
+    prelude::*,
+    sprite::Anchor,
+};
+
+use crate::{
+    dgps::DgpsEstimate,
+    floppy::{FloppyBody, FloppyComponent},
+    moveable::Moveable,
+    satellite::RangeNoise,
+    scatter::FixScatter,
+    solver::PositionEstimate,
+};
+
+/// Clock error of a single receiver in world units, added to every range it measures
+#[derive(Component, Default, Clone, Copy)]
+pub struct ClockOffset(pub f32);
+
+/// Colour of the range circles and the disc under a receiver
+#[derive(Component, Clone, Copy)]
+pub struct ReceiverColor(pub Color);
+
+impl Default for ReceiverColor {
+    fn default() -> Self {
+        Self(Color::BLUE)
+    }
+}
+
+/// Colours handed out to receivers in the order they are spawned
+pub const RECEIVER_COLORS: [Color; 6] = [
+    Color::BLUE,
+    Color::FUCHSIA,
+    Color::CYAN,
+    Color::ORANGE_RED,
+    Color::LIME_GREEN,
+    Color::PURPLE,
+];
+
+/// Spawns a draggable receiver with its own clock offset and colour
+pub fn spawn_receiver(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+    clock_offset: f32,
+    color: Color,
+) -> Entity {
+    let collider = asset_server.load("Collider.png");
+    let torso = asset_server.load("Torso.png");
+    let head = asset_server.load("Head.png");
+    let left_leg = asset_server.load("LeftLeg.png");
+    let right_leg = asset_server.load("RightLeg.png");
+    let left_arm = asset_server.load("LeftArm.png");
+    let right_arm = asset_server.load("RightArm.png");
+    let mirror = [
+        (
+            vec3(-22.5, 70., 2.),
+            vec2(0.1, 0.4),
+            left_leg,
+            right_leg,
+            -30f32.to_radians(),
+            5f32.to_radians(),
+            -15f32.to_radians(),
+        ),
+        (
+            vec3(-45., 180., 4.),
+            vec2(-0.1, 0.45),
+            left_arm,
+            right_arm,
+            -30f32.to_radians(),
+            15f32.to_radians(),
+            -50f32.to_radians(),
+        ),
+    ];
+    let mut components = vec![
+        (vec3(0., 190., 3.), vec2(0., 0.5), torso, 0., 0., 0.),
+        (
+            vec3(0., 190., 2.5),
+            vec2(0., -0.4),
+            head,
+            45f32.to_radians(),
+            0.,
+            0.,
+        ),
+    ];
+    components.extend(
+        mirror
+            .iter()
+            .map(|(pos, off, l, _, x, y, vel)| (*pos, *off, l.clone(), *x, *y, *vel)),
+    );
+    components.extend(mirror.iter().map(|(pos, off, _, r, x, y, vel)| {
+        (
+            *pos * vec3(-1., 1., 1.),
+            *off * vec2(-1., 1.),
+            r.clone(),
+            *x,
+            -*y,
+            -*vel,
+        )
+    }));
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: collider,
+                transform: Transform::from_translation(position.extend(0.5)),
+                sprite: Sprite { color, ..default() },
+                ..default()
+            },
+            Moveable {
+                radius: 30.,
+                velocity: vec2(0., 0.),
+            },
+            FloppyBody::default(),
+            PositionEstimate::default(),
+            DgpsEstimate::default(),
+            FixScatter::default(),
+            RangeNoise::default(),
+            ClockOffset(clock_offset),
+            ReceiverColor(color),
+        ))
+        .with_children(|builder| {
+            for (pos, anchor, img, x_flop, y_flop, magnitude_flop) in components {
+                builder.spawn((
+                    SpriteBundle {
+                        texture: img,
+                        transform: Transform::from_translation(pos),
+                        sprite: Sprite {
+                            anchor: Anchor::Custom(anchor),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    FloppyComponent {
+                        x_flop,
+                        y_flop,
+                        magnitude_flop,
+                    },
+                ));
+            }
+        })
+        .id()
+}
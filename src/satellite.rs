@@ -1,91 +1,205 @@
-use bevy::{math::vec2, prelude::*};
+use std::time::Duration;
 
-use crate::{floppy::FloppyBody, GraphVisibility, Settings};
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    clock::{ClockSet, SimClock},
+    gps::ionosphere_obliquity,
+    moveable::{Deletable, Moveable},
+    orbit::Orbit,
+};
 
 pub struct SatellitePlugin;
+#[derive(Clone, PartialEq, Eq, Debug, Hash, SystemSet)]
+pub struct RangeSampleSet;
 
 impl Plugin for SatellitePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, draw_satellite_circle);
+        app.insert_resource(RangeErrorSampler {
+            rng: StdRng::seed_from_u64(0),
+            timer: Timer::from_seconds(0.5, TimerMode::Repeating),
+        })
+        .add_systems(
+            Update,
+            sample_range_errors.in_set(RangeSampleSet).after(ClockSet),
+        );
     }
 }
 
 #[derive(Component)]
 pub struct Satellite;
 
-fn draw_satellite_circle(
-    mut gizmos: Gizmos,
-    settings: Res<Settings>,
-    player_query: Query<&Transform, With<FloppyBody>>,
-    satellite_query: Query<&Transform, (With<Satellite>, Without<FloppyBody>)>,
-) {
-    for player in player_query.iter() {
-        let player = player.translation.xy();
-        let mut last = None;
-        for satellite in satellite_query.iter() {
-            let p = satellite.translation.xy();
-            let dst = p.distance(player);
-            if settings.ranges_visible {
-                gizmos
-                    .circle_2d(p, dst + settings.ranges_offset, Color::BLUE)
-                    .segments(256);
+/// Spawns a draggable satellite on a circular orbit around `earth_centre`
+pub fn spawn_satellite(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+    earth_centre: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: asset_server.load("Satellite.png"),
+                transform: Transform::from_translation(position.extend(1.)),
+                ..default()
+            },
+            Moveable {
+                radius: 50.,
+                velocity: Vec2::ZERO,
+            },
+            Satellite,
+            RangeError::default(),
+            Orbit::circular_through(earth_centre, position),
+            Deletable,
+        ))
+        .id()
+}
+
+/// One contribution to the error of a measured range
+#[derive(Clone)]
+pub enum ErrorSource {
+    /// Zero mean noise with the given standard deviation
+    Gaussian { std_dev: f32 },
+    /// Constant offset, eg. a satellite clock error
+    Bias(f32),
+    /// Occasional reflected signal that arrives late
+    Multipath { probability: f32, magnitude: f32 },
+    /// Delay straight up, grows as the satellite gets closer to the horizon
+    Ionosphere { zenith_delay: f32 },
+}
+
+/// Error model for the ranges measured to a satellite, the random part is drawn by each receiver into its [`RangeNoise`]
+#[derive(Component, Clone)]
+pub struct RangeError {
+    pub sources: Vec<ErrorSource>,
+}
+
+impl RangeError {
+    pub fn new(sources: Vec<ErrorSource>) -> Self {
+        Self { sources }
+    }
+
+    /// Draws a new value for the random sources
+    pub fn sample_random(&self, rng: &mut impl Rng) -> f32 {
+        let mut random = 0.;
+        for source in self.sources.iter() {
+            match *source {
+                ErrorSource::Gaussian { std_dev } => random += gaussian(rng) * std_dev,
+                ErrorSource::Multipath {
+                    probability,
+                    magnitude,
+                } if rng.gen::<f32>() < probability => random += rng.gen::<f32>() * magnitude,
+                _ => (),
             }
-            if settings.graph_visibility == GraphVisibility::Some {
-                if let Some((lst, lstr)) = last {
-                    darw_arcs(&mut gizmos, p, dst, lst, lstr)
-                } else {
-                    gizmos.circle_2d(p, 200., Color::RED);
+        }
+        random
+    }
+
+    /// Variance of the random part of the error
+    pub fn variance(&self) -> f32 {
+        let mut variance = 0.;
+        for source in self.sources.iter() {
+            match *source {
+                ErrorSource::Gaussian { std_dev } => variance += std_dev.powi(2),
+                ErrorSource::Multipath {
+                    probability,
+                    magnitude,
+                } => {
+                    // spike is uniform in [0, magnitude] with the given probability
+                    variance += magnitude.powi(2) * (probability / 3. - probability.powi(2) / 4.)
                 }
+                _ => (),
             }
-            last = Some((p, dst))
         }
-        if settings.graph_visibility != GraphVisibility::All {
-            continue;
-        }
-        for [s1, s2] in satellite_query.iter_combinations() {
-            let s1 = s1.translation.xy();
-            let s2 = s2.translation.xy();
-            darw_arcs(
-                &mut gizmos,
-                s1,
-                s1.distance(player),
-                s2,
-                s2.distance(player),
-            );
+        variance
+    }
+
+    /// Part of the error that every receiver sees the same, `up` is the receiver's local up
+    pub fn systematic_error(&self, satellite: Vec2, receiver: Vec2, up: Vec2) -> f32 {
+        let mut err = 0.;
+        for source in self.sources.iter() {
+            match *source {
+                ErrorSource::Bias(bias) => err += bias,
+                ErrorSource::Ionosphere { zenith_delay } => {
+                    err += zenith_delay * ionosphere_obliquity(satellite, receiver, up)
+                }
+                _ => (),
+            }
         }
+        err
     }
 }
 
-fn darw_arcs(gizmos: &mut Gizmos, p1: Vec2, r1: f32, p2: Vec2, r2: f32) {
-    let d = p1.distance(p2);
-    let a: f32 = (r1 + r2 - d) * 0.5f32;
-    let step = 5.;
-    let amt = 100.;
-    let n = ((amt / step) as f32).ceil() as usize;
-    let mut v1 = Vec::with_capacity(n + 1);
-    let mut v2 = Vec::with_capacity(n + 1);
-    for i in 0..n {
-        let off = -a + (i as f32 + 0.5).powi(3) * step;
-        let [p1, p2] = circle_intersect(p1, r1 + off, p2, r2 + off);
-        if v1.is_empty() {
-            v1.push(p2);
-        }
-        v1.push(p1);
-        v2.push(p2);
+impl Default for RangeError {
+    fn default() -> Self {
+        Self::new(vec![
+            ErrorSource::Gaussian { std_dev: 8. },
+            ErrorSource::Multipath {
+                probability: 0.05,
+                magnitude: 80.,
+            },
+            ErrorSource::Ionosphere { zenith_delay: 10. },
+        ])
+    }
+}
+
+/// Last drawn random error of a receiver to each satellite, so two receivers never share their noise
+#[derive(Component, Default)]
+pub struct RangeNoise(pub HashMap<Entity, f32>);
+
+impl RangeNoise {
+    /// Noise to `satellite`, 0 before it has been drawn
+    pub fn get(&self, satellite: Entity) -> f32 {
+        self.0.get(&satellite).copied().unwrap_or(0.)
     }
-    gizmos.linestrip_2d(v1, Color::GREEN);
-    gizmos.linestrip_2d(v2, Color::GREEN);
 }
 
-// from https://stackoverflow.com/questions/3349125/circle-circle-intersection-points, translated by ChatGPT with manual edits
-fn circle_intersect(p1: Vec2, r1: f32, p2: Vec2, r2: f32) -> [Vec2; 2] {
-    let d = p1.distance(p2);
-    let a = (r1.powi(2) - r2.powi(2) + d.powi(2)) / (2.0 * d);
-    let h = (r1.powi(2) - a.powi(2)).sqrt();
-
-    let x2 = p1.x + a * (p2.x - p1.x) / d;
-    let y2 = p1.y + a * (p2.y - p1.y) / d;
-    let v1 = h * (p2.y - p1.y) / d;
-    let v2 = h * (p2.x - p1.x) / d;
-    [vec2(x2 + v1, y2 - v2), vec2(x2 - v1, y2 + v2)]
+/// Measured range from `receiver` to a satellite, including its errors and the receiver clock offset.
+/// `noise` is the receiver's random error to it, `up` its local up, eg. from `Settings::local_up`
+pub fn measured_range(
+    satellite: Vec2,
+    error: Option<&RangeError>,
+    noise: f32,
+    receiver: Vec2,
+    up: Vec2,
+    clock_offset: f32,
+) -> f32 {
+    satellite.distance(receiver)
+        + error.map_or(0., |e| e.systematic_error(satellite, receiver, up))
+        + noise
+        + clock_offset
+}
+
+// Box-Muller, saves pulling in rand_distr
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1 = rng.gen::<f32>().max(f32::MIN_POSITIVE);
+    let u2 = rng.gen::<f32>();
+    (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+#[derive(Resource)]
+struct RangeErrorSampler {
+    rng: StdRng,
+    timer: Timer,
+}
+
+fn sample_range_errors(
+    clock: Res<SimClock>,
+    mut sampler: ResMut<RangeErrorSampler>,
+    mut receiver_query: Query<&mut RangeNoise>,
+    satellite_query: Query<(Entity, &RangeError)>,
+) {
+    let delta = Duration::from_secs_f32(clock.delta_seconds());
+    if !sampler.timer.tick(delta).just_finished() {
+        return;
+    }
+    for mut noise in receiver_query.iter_mut() {
+        noise.0.clear();
+        for (satellite, error) in satellite_query.iter() {
+            noise
+                .0
+                .insert(satellite, error.sample_random(&mut sampler.rng));
+        }
+    }
 }
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    clock::SimClock,
+    floppy::FloppyBody,
+    gps::{solve_position, solve_position_and_bias, RangeMeasurement},
+    moveable::CurMoving,
+    receiver::ClockOffset,
+    satellite::{RangeError, RangeSampleSet, Satellite},
+    Settings, SimulationSet,
+};
+
+pub struct ScatterPlugin;
+
+impl Plugin for ScatterPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScatterRng(StdRng::seed_from_u64(1)))
+            .add_systems(
+                Update,
+                (reset_scatter, sample_scatter)
+                    .chain()
+                    .in_set(SimulationSet)
+                    .after(RangeSampleSet),
+            );
+    }
+}
+
+/// Rolling buffer of fixes computed from independently sampled noisy ranges
+#[derive(Component)]
+pub struct FixScatter {
+    pub samples: VecDeque<Vec2>,
+    pub capacity: usize,
+    /// Fixes computed each frame
+    pub rate: usize,
+}
+
+impl Default for FixScatter {
+    fn default() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity: 500,
+            rate: 5,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ScatterRng(StdRng);
+
+/// Starts the cloud over when the scene is edited, satellites moving along their orbits keep it
+fn reset_scatter(
+    settings: Res<Settings>,
+    cur_moving: Option<Res<CurMoving>>,
+    mut removed: RemovedComponents<Satellite>,
+    added_query: Query<(), Added<Satellite>>,
+    moved_query: Query<(), (With<Satellite>, Changed<Transform>)>,
+    mut player_query: Query<(Ref<Transform>, &mut FixScatter), With<FloppyBody>>,
+) {
+    let dragged = cur_moving
+        .and_then(|c| c.entity)
+        .is_some_and(|e| moved_query.contains(e));
+    // removed has to be read every frame, otherwise the events pile up
+    let geometry_changed =
+        removed.read().count() > 0 || !added_query.is_empty() || dragged || settings.is_changed();
+    for (transform, mut scatter) in player_query.iter_mut() {
+        if geometry_changed || transform.is_changed() || !settings.scatter_visible {
+            scatter.samples.clear();
+        }
+    }
+}
+
+fn sample_scatter(
+    settings: Res<Settings>,
+    clock: Res<SimClock>,
+    mut rng: ResMut<ScatterRng>,
+    mut player_query: Query<(&Transform, Option<&ClockOffset>, &mut FixScatter), With<FloppyBody>>,
+    satellite_query: Query<(&Transform, &RangeError, &InheritedVisibility), With<Satellite>>,
+) {
+    // freeze the cloud while the clock is paused
+    if !settings.scatter_visible || clock.delta_seconds() == 0. {
+        return;
+    }
+    for (player, offset, mut scatter) in player_query.iter_mut() {
+        let player = player.translation.xy();
+        let offset = settings.clock_offset(offset);
+        let up = settings.local_up(player);
+        for _ in 0..scatter.rate {
+            let measurements: Vec<_> = satellite_query
+                .iter()
+                .filter(|(satellite, _, vis)| {
+                    vis.get() && settings.in_view(player, satellite.translation.xy())
+                })
+                .map(|(satellite, error, _)| {
+                    let p = satellite.translation.xy();
+                    RangeMeasurement {
+                        satellite: p,
+                        range: p.distance(player)
+                            + error.systematic_error(p, player, up)
+                            + error.sample_random(&mut rng.0)
+                            + offset,
+                        variance: error.variance(),
+                    }
+                })
+                .collect();
+            let fix = if settings.solve_clock_bias {
+                solve_position_and_bias(&measurements, player).map(|(pos, _)| pos)
+            } else {
+                solve_position(&measurements, player)
+            };
+            let Some(fix) = fix else {
+                continue;
+            };
+            if scatter.samples.len() >= scatter.capacity {
+                scatter.samples.pop_front();
+            }
+            scatter.samples.push_back(fix);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::CameraController,
+    history::HistoryControl,
+    input::{Action, InputMap},
+    moveable::Deletable,
+    satellite::{spawn_satellite, Satellite},
+    write_asset_file, Character, GraphVisibility, Settings,
+};
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Scenario>()
+            .register_asset_loader(ScenarioLoader)
+            .add_event::<LoadScenario>()
+            .add_event::<SaveScenario>()
+            .add_event::<HistoryControl>()
+            .insert_resource(PendingScenario {
+                handle: None,
+                pending: false,
+            })
+            .add_systems(
+                Update,
+                (
+                    scenario_keys,
+                    save_scenario,
+                    start_loading_scenario,
+                    apply_scenario,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Where the save and load shortcuts put the scenario, relative to `assets/`
+pub const SAVED_SCENARIO: &str = "scenarios/saved.scenario.ron";
+
+/// Scenarios shipped in `assets/scenarios`, with the name shown in the picker
+pub const PRESETS: &[(&str, &str)] = &[
+    ("Two sats", "scenarios/two_satellites.scenario.ron"),
+    ("Spread", "scenarios/three_spread.scenario.ron"),
+    ("Collinear", "scenarios/collinear.scenario.ron"),
+    ("Poor geometry", "scenarios/poor_geometry.scenario.ron"),
+    ("Clock offset", "scenarios/clock_offset.scenario.ron"),
+];
+
+/// Everything needed to recreate a scene, stored as `.scenario.ron` in the assets folder
+#[derive(Asset, TypePath, Serialize, Deserialize)]
+pub struct Scenario {
+    pub satellites: Vec<Vec2>,
+    pub character: Vec2,
+    pub ranges_visible: bool,
+    pub graph_visibility: GraphVisibility,
+    pub ranges_offset: f32,
+    pub camera_position: Vec2,
+    pub camera_zoom: f32,
+}
+
+/// Request to load the scenario at the given asset path
+#[derive(Event)]
+pub struct LoadScenario(pub String);
+
+/// Request to write the current scene to the given asset path
+#[derive(Event)]
+pub struct SaveScenario(pub String);
+
+#[derive(Default)]
+struct ScenarioLoader;
+
+#[derive(Debug)]
+pub enum ScenarioLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for ScenarioLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioLoaderError::Io(err) => write!(f, "could not read scenario: {}", err),
+            ScenarioLoaderError::Ron(err) => write!(f, "could not parse scenario: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioLoaderError {}
+
+impl AssetLoader for ScenarioLoader {
+    type Asset = Scenario;
+    type Settings = ();
+    type Error = ScenarioLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Scenario, ScenarioLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(ScenarioLoaderError::Io)?;
+            ron::de::from_bytes(&bytes).map_err(ScenarioLoaderError::Ron)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scenario.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct PendingScenario {
+    handle: Option<Handle<Scenario>>,
+    /// Set until the requested scenario has finished (re)loading
+    pending: bool,
+}
+
+fn scenario_keys(
+    key: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut save_evw: EventWriter<SaveScenario>,
+    mut load_evw: EventWriter<LoadScenario>,
+) {
+    if input_map.just_pressed(&key, Action::SaveScenario) {
+        save_evw.send(SaveScenario(SAVED_SCENARIO.to_string()));
+    }
+    if input_map.just_pressed(&key, Action::LoadScenario) {
+        load_evw.send(LoadScenario(SAVED_SCENARIO.to_string()));
+    }
+}
+
+fn save_scenario(
+    mut save_evr: EventReader<SaveScenario>,
+    settings: Res<Settings>,
+    satellite_query: Query<&Transform, With<Satellite>>,
+    character_query: Query<&Transform, With<Character>>,
+    camera_query: Query<(&Transform, &CameraController)>,
+) {
+    for SaveScenario(path) in save_evr.read() {
+        let (camera_transform, camera) = camera_query.single();
+        let scenario = Scenario {
+            satellites: satellite_query.iter().map(|t| t.translation.xy()).collect(),
+            character: character_query
+                .get_single()
+                .map_or(Vec2::ZERO, |t| t.translation.xy()),
+            ranges_visible: settings.ranges_visible,
+            graph_visibility: settings.graph_visibility,
+            ranges_offset: settings.ranges_offset,
+            camera_position: camera_transform.translation.xy(),
+            camera_zoom: camera.zoom_target,
+        };
+        let result = ron::ser::to_string_pretty(&scenario, default())
+            .map_err(|err| err.to_string())
+            .and_then(|text| write_asset_file(path, &text));
+        match result {
+            Ok(()) => info!("saved scenario to {}", path),
+            Err(err) => warn!("could not save scenario to {}: {}", path, err),
+        }
+    }
+}
+
+fn start_loading_scenario(
+    asset_server: Res<AssetServer>,
+    mut load_evr: EventReader<LoadScenario>,
+    mut pending: ResMut<PendingScenario>,
+) {
+    for LoadScenario(path) in load_evr.read() {
+        let handle = asset_server.load::<Scenario>(path.clone());
+        // the file may have been saved since it was last loaded
+        asset_server.reload(path.clone());
+        pending.handle = Some(handle);
+        pending.pending = true;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_scenario(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scenarios: Res<Assets<Scenario>>,
+    mut pending: ResMut<PendingScenario>,
+    mut settings: ResMut<Settings>,
+    mut asset_evr: EventReader<AssetEvent<Scenario>>,
+    mut history_evw: EventWriter<HistoryControl>,
+    satellite_query: Query<Entity, (With<Satellite>, With<Deletable>)>,
+    mut character_query: Query<&mut Transform, With<Character>>,
+    mut camera_query: Query<(&mut Transform, &mut CameraController), Without<Character>>,
+) {
+    let Some(handle) = pending.handle.clone() else {
+        asset_evr.clear();
+        return;
+    };
+    // a missing or broken file never sends a loaded event, so stop waiting for it
+    if asset_server.load_state(&handle) == LoadState::Failed {
+        warn!("could not load scenario {:?}", handle.path());
+        pending.handle = None;
+        pending.pending = false;
+        asset_evr.clear();
+        return;
+    }
+    // read every event so they do not pile up
+    let loaded = asset_evr
+        .read()
+        .filter(|ev| {
+            matches!(ev, AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.id())
+        })
+        .count()
+        > 0;
+    if !loaded || !pending.pending {
+        return;
+    }
+    let Some(scenario) = scenarios.get(&handle) else {
+        return;
+    };
+    pending.pending = false;
+    // the edits are about entities that are gone now
+    history_evw.send(HistoryControl::Clear);
+
+    for entity in satellite_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for satellite in scenario.satellites.iter() {
+        spawn_satellite(
+            &mut commands,
+            &asset_server,
+            *satellite,
+            settings.earth_centre,
+        );
+    }
+    for mut transform in character_query.iter_mut() {
+        transform.translation = scenario.character.extend(transform.translation.z);
+    }
+    settings.ranges_visible = scenario.ranges_visible;
+    settings.graph_visibility = scenario.graph_visibility;
+    settings.ranges_offset = scenario.ranges_offset;
+    for (mut transform, mut camera) in camera_query.iter_mut() {
+        transform.translation = scenario.camera_position.extend(transform.translation.z);
+        camera.zoom_target = scenario.camera_zoom.clamp(camera.min_zoom, camera.max_zoom);
+        camera.cur_zoom = camera.zoom_target;
+    }
+}
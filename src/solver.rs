@@ -0,0 +1,93 @@
+use bevy::{math::vec2, prelude::*};
+
+use crate::{
+    floppy::FloppyBody,
+    gps::{position_covariance, solve_position, solve_position_and_bias, RangeMeasurement},
+    receiver::ClockOffset,
+    satellite::{measured_range, RangeError, RangeNoise, RangeSampleSet, Satellite},
+    Settings, SimulationSet,
+};
+
+pub struct SolverPlugin;
+
+impl Plugin for SolverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_estimates.in_set(SimulationSet).after(RangeSampleSet),
+        );
+    }
+}
+
+/// Position the receiver thinks it is at, computed from the satellite ranges
+#[derive(Component, Default)]
+pub struct PositionEstimate {
+    pub position: Option<Vec2>,
+    /// Recovered receiver clock error in world units, only set when solving for it
+    pub clock_bias: Option<f32>,
+    /// Covariance of `position` from the satellite geometry and range variances
+    pub covariance: Option<Mat2>,
+}
+
+fn update_estimates(
+    settings: Res<Settings>,
+    mut player_query: Query<
+        (
+            &Transform,
+            Option<&ClockOffset>,
+            &RangeNoise,
+            &mut PositionEstimate,
+        ),
+        With<FloppyBody>,
+    >,
+    satellite_query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&RangeError>,
+            &InheritedVisibility,
+        ),
+        With<Satellite>,
+    >,
+) {
+    for (player, offset, noise, mut estimate) in player_query.iter_mut() {
+        let player = player.translation.xy();
+        let offset = settings.clock_offset(offset);
+        let up = settings.local_up(player);
+        let measurements: Vec<_> = satellite_query
+            .iter()
+            .filter(|(_, satellite, _, vis)| {
+                vis.get() && settings.in_view(player, satellite.translation.xy())
+            })
+            .map(|(entity, satellite, error, _)| {
+                let p = satellite.translation.xy();
+                let noise = noise.get(entity);
+                RangeMeasurement {
+                    satellite: p,
+                    range: measured_range(p, error, noise, player, up, offset),
+                    variance: error.map_or(1., |e| e.variance()),
+                }
+            })
+            .collect();
+        // start from the last fix so the solution does not jump between the two
+        // mirror images when there are only two satellites
+        let initial = estimate.position.unwrap_or_else(|| {
+            measurements
+                .iter()
+                .fold(Vec2::ZERO, |acc, m| acc + m.satellite)
+                / measurements.len().max(1) as f32
+                + vec2(0., -1.)
+        });
+        if settings.solve_clock_bias {
+            let solution = solve_position_and_bias(&measurements, initial);
+            estimate.position = solution.map(|(pos, _)| pos);
+            estimate.clock_bias = solution.map(|(_, bias)| bias);
+        } else {
+            estimate.position = solve_position(&measurements, initial);
+            estimate.clock_bias = None;
+        }
+        estimate.covariance = estimate
+            .position
+            .and_then(|pos| position_covariance(&measurements, pos, settings.solve_clock_bias));
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::{asset::ron, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::{ClockSet, SimClock},
+    geo::{gpx, nmea, GeoReference},
+    gps::dilution_of_precision,
+    moveable::MoveableSet,
+    satellite::{RangeSampleSet, Satellite},
+    solver::PositionEstimate,
+    write_asset_file, Settings, SimulationSet,
+};
+
+pub struct TrackPlugin;
+
+impl Plugin for TrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrackRecorder>()
+            .add_event::<TrackControl>()
+            .add_systems(
+                Update,
+                (
+                    (handle_track_controls, replay_tracks)
+                        .chain()
+                        .after(ClockSet)
+                        .after(MoveableSet)
+                        .before(RangeSampleSet)
+                        .before(SimulationSet),
+                    record_tracks.after(SimulationSet),
+                ),
+            );
+    }
+}
+
+/// Where the export buttons write the track, relative to `assets/`
+pub const SAVED_TRACK: &str = "tracks/track.ron";
+pub const SAVED_GPX: &str = "tracks/track.gpx";
+/// The fixes go here and the true positions next to it with `_truth` added to the name
+pub const SAVED_NMEA: &str = "tracks/track.nmea";
+
+/// Position of the receiver and its fix at one moment of simulated time
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub time: f32,
+    pub truth: Vec2,
+    pub estimate: Option<Vec2>,
+    /// Satellites in view of the receiver
+    #[serde(default)]
+    pub satellites: usize,
+    #[serde(default)]
+    pub hdop: Option<f32>,
+}
+
+/// Recorded route of a receiver
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct Track {
+    pub points: Vec<TrackPoint>,
+}
+
+impl Track {
+    /// True position `time` seconds into the track, `None` once it has ended
+    pub fn position_at(&self, time: f32) -> Option<Vec2> {
+        let first = self.points.first()?;
+        let time = first.time + time;
+        if time > self.points.last()?.time {
+            return None;
+        }
+        let i = self.points.partition_point(|p| p.time < time);
+        if i == 0 {
+            return Some(first.truth);
+        }
+        let a = self.points[i - 1];
+        let b = self.points[i];
+        let t = (time - a.time) / (b.time - a.time).max(f32::EPSILON);
+        Some(a.truth.lerp(b.truth, t.clamp(0., 1.)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum TrackMode {
+    #[default]
+    Idle,
+    Recording,
+    /// Moving the receivers along their tracks, started at this simulated time
+    Replaying(f32),
+}
+
+#[derive(Resource)]
+pub struct TrackRecorder {
+    pub mode: TrackMode,
+    /// Simulated seconds between recorded points
+    pub interval: f32,
+    /// Recording stops once a track has this many points
+    pub max_points: usize,
+}
+
+impl Default for TrackRecorder {
+    fn default() -> Self {
+        Self {
+            mode: TrackMode::Idle,
+            interval: 0.1,
+            max_points: 10000,
+        }
+    }
+}
+
+#[derive(Event, Clone)]
+pub enum TrackControl {
+    /// Start a new recording, dropping the old tracks
+    Record,
+    Stop,
+    Replay,
+    Clear,
+    /// Write the tracks to the given path relative to `assets/`
+    Export(String),
+    ExportGpx(String),
+    /// Write NMEA sentences for the first track, see [`SAVED_NMEA`]
+    ExportNmea(String),
+}
+
+fn handle_track_controls(
+    clock: Res<SimClock>,
+    reference: Res<GeoReference>,
+    mut recorder: ResMut<TrackRecorder>,
+    mut control_evr: EventReader<TrackControl>,
+    mut track_query: Query<&mut Track>,
+) {
+    for control in control_evr.read() {
+        match control {
+            TrackControl::Record => {
+                for mut track in track_query.iter_mut() {
+                    track.points.clear();
+                }
+                recorder.mode = TrackMode::Recording;
+            }
+            TrackControl::Stop => recorder.mode = TrackMode::Idle,
+            TrackControl::Replay => recorder.mode = TrackMode::Replaying(clock.elapsed_seconds()),
+            TrackControl::Clear => {
+                for mut track in track_query.iter_mut() {
+                    track.points.clear();
+                }
+                if recorder.mode != TrackMode::Recording {
+                    recorder.mode = TrackMode::Idle;
+                }
+            }
+            TrackControl::Export(path) => {
+                let tracks: Vec<_> = track_query.iter().collect();
+                let result = ron::ser::to_string_pretty(&tracks, default())
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| write_asset_file(path, &text));
+                report_export(path, result);
+            }
+            TrackControl::ExportGpx(path) => {
+                let tracks: Vec<_> = track_query.iter().collect();
+                report_export(path, write_asset_file(path, &gpx(&reference, &tracks)));
+            }
+            TrackControl::ExportNmea(path) => {
+                let Some(track) = track_query.iter().next() else {
+                    continue;
+                };
+                let truth_path = match path.rsplit_once('.') {
+                    Some((stem, ext)) => format!("{}_truth.{}", stem, ext),
+                    None => format!("{}_truth", path),
+                };
+                report_export(
+                    path,
+                    write_asset_file(path, &nmea(&reference, &track.points, true)),
+                );
+                report_export(
+                    &truth_path,
+                    write_asset_file(&truth_path, &nmea(&reference, &track.points, false)),
+                );
+            }
+        }
+    }
+}
+
+fn report_export(path: &str, result: Result<(), String>) {
+    match result {
+        Ok(()) => info!("exported tracks to {}", path),
+        Err(err) => warn!("could not export tracks to {}: {}", path, err),
+    }
+}
+
+fn replay_tracks(
+    clock: Res<SimClock>,
+    mut recorder: ResMut<TrackRecorder>,
+    mut query: Query<(&Track, &mut Transform)>,
+) {
+    let TrackMode::Replaying(start) = recorder.mode else {
+        return;
+    };
+    let mut playing = false;
+    for (track, mut transform) in query.iter_mut() {
+        // tracks that have ended wait at their last point for the others
+        let pos = match track.position_at(clock.elapsed_seconds() - start) {
+            Some(pos) => {
+                playing = true;
+                pos
+            }
+            None => match track.points.last() {
+                Some(last) => last.truth,
+                None => continue,
+            },
+        };
+        if transform.translation.xy() != pos {
+            transform.translation = pos.extend(transform.translation.z);
+        }
+    }
+    if !playing {
+        recorder.mode = TrackMode::Idle;
+    }
+}
+
+fn record_tracks(
+    clock: Res<SimClock>,
+    settings: Res<Settings>,
+    recorder: Res<TrackRecorder>,
+    mut query: Query<(&Transform, Option<&PositionEstimate>, &mut Track)>,
+    satellite_query: Query<&Transform, With<Satellite>>,
+) {
+    if recorder.mode != TrackMode::Recording || clock.delta_seconds() == 0. {
+        return;
+    }
+    let time = clock.elapsed_seconds();
+    for (transform, estimate, mut track) in query.iter_mut() {
+        let due = track
+            .points
+            .last()
+            .map_or(true, |p| time - p.time >= recorder.interval);
+        if !due || track.points.len() >= recorder.max_points {
+            continue;
+        }
+        let truth = transform.translation.xy();
+        let satellites: Vec<_> = satellite_query
+            .iter()
+            .map(|t| t.translation.xy())
+            .filter(|p| settings.in_view(truth, *p))
+            .collect();
+        track.points.push(TrackPoint {
+            time,
+            truth,
+            estimate: estimate.and_then(|e| e.position),
+            satellites: satellites.len(),
+            hdop: dilution_of_precision(&satellites, truth).map(|dop| dop.hdop),
+        });
+    }
+}
@@ -1,10 +1,23 @@
-use bevy::{math::vec3, prelude::*};
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{math::vec2, prelude::*};
 use rand::prelude::*;
 
 use crate::{
-    moveable::{Deletable, Moveable},
+    clock::SimClock,
+    floppy::FloppyBody,
+    gps::dilution_of_precision,
+    history::Spawned,
+    input::{Action, InputMap},
+    moveable::Deletable,
+    nmea::{LoadNmea, NMEA_LOG},
+    placement::Placement,
+    receiver::{spawn_receiver, ClockOffset, RECEIVER_COLORS},
     satellite::Satellite,
-    GraphVisibility, Settings,
+    scenario::{LoadScenario, PRESETS},
+    solver::PositionEstimate,
+    track::{TrackControl, SAVED_GPX, SAVED_NMEA, SAVED_TRACK},
+    Character, GraphVisibility, Settings,
 };
 
 pub struct UiPlugin;
@@ -15,10 +28,36 @@ impl Plugin for UiPlugin {
             Update,
             (
                 state_button_interaction,
-                sat_button_interaction,
-                range_button_interaction,
-                vis_button_interaction,
-            ),
+                (
+                    sat_button_interaction,
+                    receiver_button_interaction,
+                    range_button_interaction,
+                    mask_button_interaction,
+                    vis_button_interaction,
+                    toggle_button_interaction,
+                    scenario_button_interaction,
+                    log_button_interaction,
+                    track_button_interaction,
+                    clock_button_interaction,
+                    keyboard_shortcuts,
+                    receiver_shortcut,
+                    clock_shortcuts,
+                    track_shortcuts,
+                ),
+                (
+                    sync_state_buttons,
+                    sync_toggle_buttons,
+                    sync_satellite_button,
+                    sync_pause_button,
+                ),
+                (
+                    update_clock_bias_text,
+                    update_dop_text,
+                    update_clock_text,
+                    update_mask_text,
+                ),
+            )
+                .chain(),
         );
     }
 }
@@ -26,96 +65,576 @@ impl Plugin for UiPlugin {
 struct SatelliteButton;
 
 #[derive(Component)]
-enum RangeButton {
+struct ReceiverButton;
+
+/// Changes a value while held down, with the seconds it has been held for
+#[derive(Component)]
+enum HoldButton {
     Increase(f32),
     Decrease(f32),
     Reset,
 }
 
+/// What a pressed hold button does this frame
+enum HoldAction {
+    /// `sign` is 1 or -1, `held` the seconds held before this frame
+    Change {
+        sign: f32,
+        held: f32,
+    },
+    Reset,
+}
+
+impl HoldButton {
+    /// Counts up the time held while pressed, and starts over once released
+    fn hold(&mut self, interaction: Interaction, delta: f32) -> Option<HoldAction> {
+        let pressed = interaction == Interaction::Pressed;
+        let (sign, held) = match self {
+            HoldButton::Increase(held) => (1., held),
+            HoldButton::Decrease(held) => (-1., held),
+            HoldButton::Reset => return pressed.then_some(HoldAction::Reset),
+        };
+        if !pressed {
+            *held = 0.;
+            return None;
+        }
+        let before = *held;
+        *held += delta;
+        Some(HoldAction::Change { sign, held: before })
+    }
+}
+
+#[derive(Component)]
+struct RangeButton;
+
+#[derive(Component)]
+struct MaskButton;
+
 #[derive(Component)]
 enum VisibilityButton {
     Character,
     Ranges,
     Graphs,
+    Scatter,
+}
+
+/// On/off buttons for settings that are not about visibility
+#[derive(Component)]
+enum ToggleButton {
+    SolveClock,
+    Orbits,
+    Earth,
+    Dgps,
+    View3d,
+}
+
+#[derive(Component)]
+struct ScenarioButton(&'static str);
+
+#[derive(Component)]
+struct LogButton;
+
+#[derive(Component, Clone, Copy)]
+enum TrackButton {
+    Record,
+    Stop,
+    Replay,
+    Clear,
+    Export,
+    ExportGpx,
+    ExportNmea,
+}
+
+#[derive(Component)]
+enum ClockButton {
+    Pause,
+    Step,
+    Slower,
+    Faster,
+    ResetSpeed,
 }
 
+#[derive(Component)]
+struct ClockText;
+
+#[derive(Component)]
+struct ClockBiasText;
+
+#[derive(Component)]
+struct DopText;
+
+#[derive(Component)]
+struct MaskText;
+
+/// Arms the placement tool, pressing again while armed cancels it
 fn sat_button_interaction(
+    mut placement: ResMut<Placement>,
+    query: Query<(&Interaction, &SatelliteButton, &StateButton), Changed<Interaction>>,
+) {
+    for (interaction, _, state) in query.iter() {
+        if *interaction == Interaction::Pressed {
+            placement.armed = state.state == 1;
+        }
+    }
+}
+
+/// Adds a receiver with a random clock offset next to the origin
+fn receiver_button_interaction(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    query: Query<&Interaction, (Changed<Interaction>, With<SatelliteButton>)>,
+    mut spawned_evw: EventWriter<Spawned>,
+    receiver_query: Query<(), With<FloppyBody>>,
+    mut query: Query<(&Interaction, &ReceiverButton, &mut BackgroundColor), Changed<Interaction>>,
 ) {
-    let mut rng = thread_rng();
-    for interaction in query.iter() {
-        if *interaction == Interaction::Pressed {
-            commands.spawn((
-                SpriteBundle {
-                    texture: asset_server.load("Satellite.png"),
-                    transform: Transform::from_translation(vec3(
-                        rng.gen::<f32>() * 2000. - 1000.,
-                        rng.gen::<f32>() * 2000. - 1000.,
-                        1.,
-                    )),
-                    ..default()
-                },
-                Moveable {
-                    radius: 50.,
-                    velocity: Vec2::ZERO,
-                },
-                Satellite,
-                Deletable,
-            ));
+    for (interaction, _, mut color) in query.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => {
+                let count = receiver_query.iter().count();
+                let entity = spawn_random_receiver(&mut commands, &asset_server, count);
+                spawned_evw.send(Spawned(entity));
+                TEXT_BUTTON_HOVERED
+            }
+            Interaction::Hovered => TEXT_BUTTON_HOVERED,
+            Interaction::None => TEXT_BUTTON,
         }
+        .into();
     }
 }
 
+fn receiver_shortcut(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    key: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut spawned_evw: EventWriter<Spawned>,
+    receiver_query: Query<(), With<FloppyBody>>,
+) {
+    if input_map.just_pressed(&key, Action::AddReceiver) {
+        let count = receiver_query.iter().count();
+        let entity = spawn_random_receiver(&mut commands, &asset_server, count);
+        spawned_evw.send(Spawned(entity));
+    }
+}
+
+/// `count` is the number of receivers already there, which picks the colour
+fn spawn_random_receiver(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    count: usize,
+) -> Entity {
+    let mut rng = thread_rng();
+    let entity = spawn_receiver(
+        commands,
+        asset_server,
+        vec2(
+            rng.gen::<f32>() * 1000. - 500.,
+            rng.gen::<f32>() * 400. - 200.,
+        ),
+        rng.gen::<f32>() * 400. - 200.,
+        RECEIVER_COLORS[count % RECEIVER_COLORS.len()],
+    );
+    commands.entity(entity).insert(Deletable);
+    entity
+}
+
 fn vis_button_interaction(
     mut settings: ResMut<Settings>,
-    query: Query<(&VisibilityButton, &StateButton), Changed<Interaction>>,
+    query: Query<(&Interaction, &VisibilityButton, &StateButton), Changed<Interaction>>,
 ) {
-    for (vis, state) in query.iter() {
-        match *vis {
-            VisibilityButton::Character => settings.character_visible = state.state == 0,
-            VisibilityButton::Ranges => settings.ranges_visible = state.state == 1,
-            VisibilityButton::Graphs => {
-                settings.graph_visibility = match state.state {
-                    1 => GraphVisibility::Some,
-                    2 => GraphVisibility::All,
-                    _ => GraphVisibility::None,
-                }
+    for (interaction, vis, state) in query.iter() {
+        if *interaction == Interaction::Pressed {
+            set_visibility(&mut settings, vis, state.state);
+        }
+    }
+}
+
+/// Button state of a visibility setting, the buttons cycle through these on each press
+fn visibility_state(settings: &Settings, vis: &VisibilityButton) -> u16 {
+    match *vis {
+        VisibilityButton::Character => !settings.character_visible as u16,
+        VisibilityButton::Ranges => settings.ranges_visible as u16,
+        VisibilityButton::Graphs => match settings.graph_visibility {
+            GraphVisibility::None => 0,
+            GraphVisibility::Some => 1,
+            GraphVisibility::All => 2,
+        },
+        VisibilityButton::Scatter => settings.scatter_visible as u16,
+    }
+}
+
+fn set_visibility(settings: &mut Settings, vis: &VisibilityButton, state: u16) {
+    match *vis {
+        VisibilityButton::Character => settings.character_visible = state == 0,
+        VisibilityButton::Ranges => settings.ranges_visible = state == 1,
+        VisibilityButton::Scatter => settings.scatter_visible = state == 1,
+        VisibilityButton::Graphs => {
+            settings.graph_visibility = match state {
+                1 => GraphVisibility::Some,
+                2 => GraphVisibility::All,
+                _ => GraphVisibility::None,
             }
         }
     }
 }
 
-fn range_button_interaction(
+/// Change of `Settings::ranges_offset` over `delta` seconds, speeding up the longer it is held
+fn offset_change(held: f32, delta: f32) -> f32 {
+    delta * 250. * (held + 1.)
+}
+
+/// Change of `Settings::elevation_mask` over `delta` seconds, speeding up the same way
+fn mask_change(held: f32, delta: f32) -> f32 {
+    delta * 10f32.to_radians() * (held + 1.)
+}
+
+fn change_mask(settings: &mut Settings, change: f32) {
+    settings.elevation_mask = (settings.elevation_mask + change).clamp(-FRAC_PI_2, FRAC_PI_2);
+}
+
+/// Seconds the increase or decrease keys have been held for
+#[derive(Default)]
+struct HeldKeys {
+    offset: f32,
+    mask: f32,
+}
+
+/// Direction of a pair of held actions, 0 when neither or both are held
+fn held_sign(key: &ButtonInput<KeyCode>, input_map: &InputMap, up: Action, down: Action) -> f32 {
+    let up = input_map.pressed(key, up) as i8;
+    let down = input_map.pressed(key, down) as i8;
+    (up - down) as f32
+}
+
+/// Runs the keys from the `InputMap` through the same changes as the buttons
+fn keyboard_shortcuts(
     time: Res<Time>,
+    key: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
     mut settings: ResMut<Settings>,
-    mut button_query: Query<(&Interaction, &mut RangeButton)>,
+    mut placement: ResMut<Placement>,
+    mut held: Local<HeldKeys>,
+) {
+    if input_map.just_pressed(&key, Action::AddSatellite) {
+        placement.armed = true;
+    }
+    for (action, vis, states) in [
+        (Action::ToggleCharacter, VisibilityButton::Character, 2),
+        (Action::ToggleRanges, VisibilityButton::Ranges, 2),
+        (Action::CycleGraphs, VisibilityButton::Graphs, 3),
+        (Action::ToggleScatter, VisibilityButton::Scatter, 2),
+    ] {
+        if input_map.just_pressed(&key, action) {
+            let state = (visibility_state(&settings, &vis) + 1) % states;
+            set_visibility(&mut settings, &vis, state);
+        }
+    }
+    for (action, toggle) in [
+        (Action::ToggleSolveClock, ToggleButton::SolveClock),
+        (Action::ToggleOrbits, ToggleButton::Orbits),
+        (Action::ToggleEarth, ToggleButton::Earth),
+        (Action::ToggleDgps, ToggleButton::Dgps),
+        (Action::Toggle3d, ToggleButton::View3d),
+    ] {
+        if input_map.just_pressed(&key, action) {
+            let on = toggle_state(&settings, &toggle);
+            set_toggle(&mut settings, &toggle, !on);
+        }
+    }
+
+    let delta = time.delta_seconds();
+    let sign = held_sign(
+        &key,
+        &input_map,
+        Action::IncreaseOffset,
+        Action::DecreaseOffset,
+    );
+    if sign != 0. {
+        settings.ranges_offset += sign * offset_change(held.offset, delta);
+        held.offset += delta;
+    } else {
+        held.offset = 0.;
+    }
+    if input_map.just_pressed(&key, Action::ResetOffset) {
+        settings.ranges_offset = 0.;
+    }
+
+    let sign = held_sign(&key, &input_map, Action::IncreaseMask, Action::DecreaseMask);
+    if sign != 0. {
+        change_mask(&mut settings, sign * mask_change(held.mask, delta));
+        held.mask += delta;
+    } else {
+        held.mask = 0.;
+    }
+    if input_map.just_pressed(&key, Action::ResetMask) {
+        settings.elevation_mask = -FRAC_PI_2;
+    }
+}
+
+fn clock_shortcuts(
+    key: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut clock: ResMut<SimClock>,
+) {
+    if input_map.just_pressed(&key, Action::TogglePause) {
+        clock.paused = !clock.paused;
+    }
+    if input_map.just_pressed(&key, Action::Step) {
+        clock.step();
+    }
+    if input_map.just_pressed(&key, Action::Slower) {
+        let scale = clock.scale * 0.5;
+        clock.set_scale(scale);
+    }
+    if input_map.just_pressed(&key, Action::Faster) {
+        let scale = clock.scale * 2.;
+        clock.set_scale(scale);
+    }
+    if input_map.just_pressed(&key, Action::ResetSpeed) {
+        clock.set_scale(1.);
+    }
+}
+
+fn track_shortcuts(
+    key: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut control_evw: EventWriter<TrackControl>,
+) {
+    for (action, control) in [
+        (Action::RecordTrack, TrackControl::Record),
+        (Action::StopTrack, TrackControl::Stop),
+        (Action::ReplayTrack, TrackControl::Replay),
+    ] {
+        if input_map.just_pressed(&key, action) {
+            control_evw.send(control);
+        }
+    }
+}
+
+fn toggle_button_interaction(
+    mut settings: ResMut<Settings>,
+    query: Query<(&Interaction, &ToggleButton, &StateButton), Changed<Interaction>>,
+) {
+    for (interaction, toggle, state) in query.iter() {
+        if *interaction == Interaction::Pressed {
+            set_toggle(&mut settings, toggle, state.state == 1);
+        }
+    }
+}
+
+fn toggle_state(settings: &Settings, toggle: &ToggleButton) -> bool {
+    match *toggle {
+        ToggleButton::SolveClock => settings.solve_clock_bias,
+        ToggleButton::Orbits => settings.orbits_moving,
+        ToggleButton::Earth => settings.earth_enabled,
+        ToggleButton::Dgps => settings.dgps_enabled,
+        ToggleButton::View3d => settings.view_3d,
+    }
+}
+
+fn set_toggle(settings: &mut Settings, toggle: &ToggleButton, on: bool) {
+    match *toggle {
+        ToggleButton::SolveClock => settings.solve_clock_bias = on,
+        ToggleButton::Orbits => settings.orbits_moving = on,
+        ToggleButton::Earth => settings.earth_enabled = on,
+        ToggleButton::Dgps => settings.dgps_enabled = on,
+        ToggleButton::View3d => settings.view_3d = on,
+    }
+}
+
+fn scenario_button_interaction(
+    mut load_evw: EventWriter<LoadScenario>,
+    mut query: Query<(&Interaction, &ScenarioButton, &mut BackgroundColor), Changed<Interaction>>,
 ) {
-    let speed = 250.;
-    for (int, mut range) in button_query.iter_mut() {
-        if *int != Interaction::Pressed {
-            *range = match *range {
-                RangeButton::Increase(_) => RangeButton::Increase(0.),
-                RangeButton::Decrease(_) => RangeButton::Decrease(0.),
-                RangeButton::Reset => RangeButton::Reset,
-            };
+    for (interaction, scenario, mut color) in query.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => {
+                load_evw.send(LoadScenario(scenario.0.to_string()));
+                TEXT_BUTTON_HOVERED
+            }
+            Interaction::Hovered => TEXT_BUTTON_HOVERED,
+            Interaction::None => TEXT_BUTTON,
+        }
+        .into();
+    }
+}
+
+fn log_button_interaction(
+    mut load_evw: EventWriter<LoadNmea>,
+    mut query: Query<(&Interaction, &LogButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, _, mut color) in query.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => {
+                load_evw.send(LoadNmea(NMEA_LOG.to_string()));
+                TEXT_BUTTON_HOVERED
+            }
+            Interaction::Hovered => TEXT_BUTTON_HOVERED,
+            Interaction::None => TEXT_BUTTON,
+        }
+        .into();
+    }
+}
+
+fn track_button_interaction(
+    mut control_evw: EventWriter<TrackControl>,
+    mut query: Query<(&Interaction, &TrackButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, track_button, mut color) in query.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => {
+                control_evw.send(match track_button {
+                    TrackButton::Record => TrackControl::Record,
+                    TrackButton::Stop => TrackControl::Stop,
+                    TrackButton::Replay => TrackControl::Replay,
+                    TrackButton::Clear => TrackControl::Clear,
+                    TrackButton::Export => TrackControl::Export(SAVED_TRACK.to_string()),
+                    TrackButton::ExportGpx => TrackControl::ExportGpx(SAVED_GPX.to_string()),
+                    TrackButton::ExportNmea => TrackControl::ExportNmea(SAVED_NMEA.to_string()),
+                });
+                TEXT_BUTTON_HOVERED
+            }
+            Interaction::Hovered => TEXT_BUTTON_HOVERED,
+            Interaction::None => TEXT_BUTTON,
+        }
+        .into();
+    }
+}
+
+fn clock_button_interaction(
+    mut clock: ResMut<SimClock>,
+    query: Query<(&Interaction, &ClockButton, &StateButton), Changed<Interaction>>,
+) {
+    for (interaction, clock_button, state) in query.iter() {
+        if *interaction != Interaction::Pressed {
             continue;
         }
-        match *range {
-            RangeButton::Increase(b_time) => {
-                *range = RangeButton::Increase(b_time + time.delta_seconds());
-                settings.ranges_offset += time.delta_seconds() * speed * (b_time + 1.);
+        match *clock_button {
+            ClockButton::Pause => clock.paused = state.state == 1,
+            ClockButton::Step => clock.step(),
+            ClockButton::Slower => {
+                let scale = clock.scale * 0.5;
+                clock.set_scale(scale)
+            }
+            ClockButton::Faster => {
+                let scale = clock.scale * 2.;
+                clock.set_scale(scale)
             }
-            RangeButton::Decrease(b_time) => {
-                *range = RangeButton::Decrease(b_time + time.delta_seconds());
-                settings.ranges_offset -= time.delta_seconds() * speed * (b_time + 1.);
+            ClockButton::ResetSpeed => clock.set_scale(1.),
+        }
+    }
+}
+
+fn update_clock_text(clock: Res<SimClock>, mut text_query: Query<&mut Text, With<ClockText>>) {
+    if !clock.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "x{}  {:.1}s{}",
+            clock.scale,
+            clock.elapsed_seconds(),
+            if clock.paused { "  paused" } else { "" }
+        );
+    }
+}
+
+fn update_clock_bias_text(
+    settings: Res<Settings>,
+    estimate_query: Query<(&PositionEstimate, Option<&ClockOffset>), With<Character>>,
+    mut text_query: Query<&mut Text, With<ClockBiasText>>,
+) {
+    let Ok((estimate, offset)) = estimate_query.get_single() else {
+        return;
+    };
+    let recovered = match estimate.clock_bias {
+        Some(bias) => format!("{:.1}", bias),
+        None if settings.solve_clock_bias => "-".to_string(),
+        None => "off".to_string(),
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "set {:.1} / recovered {}",
+            settings.clock_offset(offset),
+            recovered
+        );
+    }
+}
+
+fn update_dop_text(
+    settings: Res<Settings>,
+    character_query: Query<&Transform, With<Character>>,
+    satellite_query: Query<&Transform, With<Satellite>>,
+    mut text_query: Query<&mut Text, With<DopText>>,
+) {
+    let Ok(character) = character_query.get_single() else {
+        return;
+    };
+    let character = character.translation.xy();
+    let satellites: Vec<_> = satellite_query
+        .iter()
+        .map(|t| t.translation.xy())
+        .filter(|p| settings.in_view(character, *p))
+        .collect();
+    let value = match dilution_of_precision(&satellites, character) {
+        Some(dop) => format!(
+            "GDOP {:.2}  HDOP {:.2}  TDOP {:.2}",
+            dop.gdop, dop.hdop, dop.tdop
+        ),
+        None => "GDOP -  HDOP -  TDOP -".to_string(),
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn range_button_interaction(
+    time: Res<Time>,
+    mut settings: ResMut<Settings>,
+    mut button_query: Query<(&Interaction, &mut HoldButton), With<RangeButton>>,
+) {
+    for (int, mut button) in button_query.iter_mut() {
+        match button.hold(*int, time.delta_seconds()) {
+            Some(HoldAction::Change { sign, held }) => {
+                settings.ranges_offset += sign * offset_change(held, time.delta_seconds());
             }
-            RangeButton::Reset => settings.ranges_offset = 0.,
+            Some(HoldAction::Reset) => settings.ranges_offset = 0.,
+            None => (),
         }
     }
 }
 
+fn mask_button_interaction(
+    time: Res<Time>,
+    mut settings: ResMut<Settings>,
+    mut button_query: Query<(&Interaction, &mut HoldButton), With<MaskButton>>,
+) {
+    for (int, mut button) in button_query.iter_mut() {
+        match button.hold(*int, time.delta_seconds()) {
+            Some(HoldAction::Change { sign, held }) => {
+                change_mask(
+                    &mut settings,
+                    sign * mask_change(held, time.delta_seconds()),
+                );
+            }
+            Some(HoldAction::Reset) => settings.elevation_mask = -FRAC_PI_2,
+            None => (),
+        }
+    }
+}
+
+fn update_mask_text(settings: Res<Settings>, mut text_query: Query<&mut Text, With<MaskText>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = if settings.elevation_mask <= -FRAC_PI_2 {
+            "off".to_string()
+        } else {
+            format!("{:.0} deg", settings.elevation_mask.to_degrees())
+        };
+    }
+}
+
 fn state_button_interaction(
     mut query: Query<
         (&Interaction, &mut StateButton, &Children),
@@ -130,21 +649,102 @@ fn state_button_interaction(
             Interaction::Hovered => state.1.clone(),
             Interaction::None => state.0.clone(),
         };
-        let mut ent = None;
-        for child in children {
-            if let Ok(_) = child_query.get(*child) {
-                ent = Some(child);
-                break;
-            }
-        }
-        let mut ui_im = child_query.get_mut(*ent.unwrap()).unwrap();
-        ui_im.texture = s;
+        set_button_image(children, &mut child_query, s);
         if *interaction == Interaction::Pressed {
             btn.state = (btn.state + 1) % btn.states.len() as u16;
         }
     }
 }
 
+/// Keeps the visibility buttons in line with `Settings` when it is changed from elsewhere, eg. by loading a scenario
+fn sync_state_buttons(
+    settings: Res<Settings>,
+    mut query: Query<(&VisibilityButton, &mut StateButton, &Children)>,
+    mut child_query: Query<&mut UiImage>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (vis, mut btn, children) in query.iter_mut() {
+        let state = visibility_state(&settings, vis);
+        sync_button(&mut btn, children, &mut child_query, state);
+    }
+}
+
+/// Same for the on/off buttons, which change from elsewhere by undoing or from the keyboard
+fn sync_toggle_buttons(
+    settings: Res<Settings>,
+    mut query: Query<(&ToggleButton, &mut StateButton, &Children)>,
+    mut child_query: Query<&mut UiImage>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (toggle, mut btn, children) in query.iter_mut() {
+        let on = toggle_state(&settings, toggle);
+        sync_button(&mut btn, children, &mut child_query, on as u16);
+    }
+}
+
+/// Keeps the pause button in line with the clock when it is paused from the keyboard
+fn sync_pause_button(
+    clock: Res<SimClock>,
+    mut query: Query<(&ClockButton, &mut StateButton, &Children)>,
+    mut child_query: Query<&mut UiImage>,
+) {
+    if !clock.is_changed() {
+        return;
+    }
+    for (clock_button, mut btn, children) in query.iter_mut() {
+        if let ClockButton::Pause = clock_button {
+            sync_button(&mut btn, children, &mut child_query, clock.paused as u16);
+        }
+    }
+}
+
+/// Lets go of the add button once the satellite is placed
+fn sync_satellite_button(
+    placement: Res<Placement>,
+    mut query: Query<(&mut StateButton, &Children), With<SatelliteButton>>,
+    mut child_query: Query<&mut UiImage>,
+) {
+    if !placement.is_changed() {
+        return;
+    }
+    for (mut btn, children) in query.iter_mut() {
+        sync_button(&mut btn, children, &mut child_query, placement.armed as u16);
+    }
+}
+
+fn sync_button(
+    btn: &mut StateButton,
+    children: &Children,
+    child_query: &mut Query<&mut UiImage>,
+    state: u16,
+) {
+    if btn.state == state {
+        return;
+    }
+    btn.state = state;
+    set_button_image(children, child_query, btn.states[state as usize].0.clone());
+}
+
+fn set_button_image(
+    children: &Children,
+    child_query: &mut Query<&mut UiImage>,
+    image: Handle<Image>,
+) {
+    let mut ent = None;
+    for child in children {
+        if let Ok(_) = child_query.get(*child) {
+            ent = Some(child);
+            break;
+        }
+    }
+    let mut ui_im = child_query.get_mut(*ent.unwrap()).unwrap();
+    ui_im.texture = image;
+}
+
 fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn(NodeBundle {
@@ -182,10 +782,17 @@ fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                     },));
                     parent
                         .spawn((
-                            button(vec![(
-                                asset_server.load("AddSat.png"),
-                                asset_server.load("AddSatSel.png"),
-                            )]),
+                            button(vec![
+                                (
+                                    asset_server.load("AddSat.png"),
+                                    asset_server.load("AddSatSel.png"),
+                                ),
+                                // armed
+                                (
+                                    asset_server.load("AddSatSel.png"),
+                                    asset_server.load("AddSatSel.png"),
+                                ),
+                            ]),
                             SatelliteButton,
                         ))
                         .with_children(|parent| {
@@ -224,7 +831,8 @@ fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                                 asset_server.load("Dec.png"),
                                 asset_server.load("DecSel.png"),
                             )]),
-                            RangeButton::Decrease(0.),
+                            HoldButton::Decrease(0.),
+                            RangeButton,
                         ))
                         .with_children(|parent| {
                             parent.spawn(ImageBundle::default());
@@ -236,7 +844,8 @@ fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                                 asset_server.load("Inc.png"),
                                 asset_server.load("IncSel.png"),
                             )]),
-                            RangeButton::Increase(0.),
+                            HoldButton::Increase(0.),
+                            RangeButton,
                         ))
                         .with_children(|parent| {
                             parent.spawn(ImageBundle::default());
@@ -248,7 +857,8 @@ fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                                 asset_server.load("Reset.png"),
                                 asset_server.load("ResetSel.png"),
                             )]),
-                            RangeButton::Reset,
+                            HoldButton::Reset,
+                            RangeButton,
                         ))
                         .with_children(|parent| {
                             parent.spawn(ImageBundle::default());
@@ -372,6 +982,364 @@ fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                             parent.spawn(ImageBundle::default());
                         });
                 });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Solve clock", text_style()));
+                    parent
+                        .spawn((
+                            button(vec![
+                                (
+                                    asset_server.load("Off.png"),
+                                    asset_server.load("OffSel.png"),
+                                ),
+                                (asset_server.load("On.png"), asset_server.load("OnSel.png")),
+                            ]),
+                            ToggleButton::SolveClock,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(ImageBundle::default());
+                        });
+                    parent.spawn((TextBundle::from_section("", text_style()), ClockBiasText));
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Scatter", text_style()));
+                    parent
+                        .spawn((
+                            button(vec![
+                                (
+                                    asset_server.load("Hidden.png"),
+                                    asset_server.load("HiddenSel.png"),
+                                ),
+                                (
+                                    asset_server.load("Shown.png"),
+                                    asset_server.load("ShownSel.png"),
+                                ),
+                            ]),
+                            VisibilityButton::Scatter,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(ImageBundle::default());
+                        });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Orbits", text_style()));
+                    parent
+                        .spawn((
+                            button(vec![
+                                (
+                                    asset_server.load("Off.png"),
+                                    asset_server.load("OffSel.png"),
+                                ),
+                                (asset_server.load("On.png"), asset_server.load("OnSel.png")),
+                            ]),
+                            ToggleButton::Orbits,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(ImageBundle::default());
+                        });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Earth", text_style()));
+                    parent
+                        .spawn((
+                            button(vec![
+                                (
+                                    asset_server.load("Off.png"),
+                                    asset_server.load("OffSel.png"),
+                                ),
+                                (asset_server.load("On.png"), asset_server.load("OnSel.png")),
+                            ]),
+                            ToggleButton::Earth,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(ImageBundle::default());
+                        });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("DGPS", text_style()));
+                    parent
+                        .spawn((
+                            button(vec![
+                                (
+                                    asset_server.load("Off.png"),
+                                    asset_server.load("OffSel.png"),
+                                ),
+                                (asset_server.load("On.png"), asset_server.load("OnSel.png")),
+                            ]),
+                            ToggleButton::Dgps,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(ImageBundle::default());
+                        });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("3D", text_style()));
+                    parent
+                        .spawn((
+                            button(vec![
+                                (
+                                    asset_server.load("Off.png"),
+                                    asset_server.load("OffSel.png"),
+                                ),
+                                (asset_server.load("On.png"), asset_server.load("OnSel.png")),
+                            ]),
+                            ToggleButton::View3d,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(ImageBundle::default());
+                        });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Mask", text_style()));
+                    for (image, image_sel, hold_button) in [
+                        ("Dec.png", "DecSel.png", HoldButton::Decrease(0.)),
+                        ("Inc.png", "IncSel.png", HoldButton::Increase(0.)),
+                        ("Reset.png", "ResetSel.png", HoldButton::Reset),
+                    ] {
+                        parent
+                            .spawn((
+                                button(vec![(
+                                    asset_server.load(image),
+                                    asset_server.load(image_sel),
+                                )]),
+                                hold_button,
+                                MaskButton,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(ImageBundle::default());
+                            });
+                    }
+                    parent.spawn((TextBundle::from_section("off", text_style()), MaskText));
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Time", text_style()));
+                    for (states, clock_button) in [
+                        (
+                            vec![("Pause.png", "PauseSel.png"), ("Play.png", "PlaySel.png")],
+                            ClockButton::Pause,
+                        ),
+                        (vec![("Step.png", "StepSel.png")], ClockButton::Step),
+                        (vec![("Dec.png", "DecSel.png")], ClockButton::Slower),
+                        (vec![("Inc.png", "IncSel.png")], ClockButton::Faster),
+                        (vec![("Reset.png", "ResetSel.png")], ClockButton::ResetSpeed),
+                    ] {
+                        parent
+                            .spawn((
+                                button(
+                                    states
+                                        .into_iter()
+                                        .map(|(s, sel)| {
+                                            (asset_server.load(s), asset_server.load(sel))
+                                        })
+                                        .collect(),
+                                ),
+                                clock_button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(ImageBundle::default());
+                            });
+                    }
+                    parent.spawn((TextBundle::from_section("", text_style()), ClockText));
+                });
+            parent.spawn((TextBundle::from_section("", text_style()), DopText));
+
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                            ..default()
+                        },
+                        background_color: TEXT_BUTTON.into(),
+                        ..default()
+                    },
+                    ReceiverButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Add receiver", text_style()));
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Track", text_style()));
+                    for (name, track_button) in [
+                        ("Record", TrackButton::Record),
+                        ("Stop", TrackButton::Stop),
+                        ("Replay", TrackButton::Replay),
+                        ("Clear", TrackButton::Clear),
+                        ("Export", TrackButton::Export),
+                        ("GPX", TrackButton::ExportGpx),
+                        ("NMEA", TrackButton::ExportNmea),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                                        ..default()
+                                    },
+                                    background_color: TEXT_BUTTON.into(),
+                                    ..default()
+                                },
+                                track_button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(name, text_style()));
+                            });
+                    }
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                                    ..default()
+                                },
+                                background_color: TEXT_BUTTON.into(),
+                                ..default()
+                            },
+                            LogButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Play log", text_style()));
+                        });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Scenario", text_style()));
+                    for (name, path) in PRESETS {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                                        ..default()
+                                    },
+                                    background_color: TEXT_BUTTON.into(),
+                                    ..default()
+                                },
+                                ScenarioButton(path),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(*name, text_style()));
+                            });
+                    }
+                });
         });
 }
 
@@ -390,3 +1358,14 @@ fn button(states: Vec<(Handle<Image>, Handle<Image>)>) -> impl Bundle {
         StateButton { state: 0, states },
     )
 }
+
+const TEXT_BUTTON: Color = Color::rgba(0., 0., 0., 0.4);
+const TEXT_BUTTON_HOVERED: Color = Color::rgba(0., 0., 0., 0.7);
+
+fn text_style() -> TextStyle {
+    TextStyle {
+        font_size: 32.,
+        color: Color::WHITE,
+        ..default()
+    }
+}
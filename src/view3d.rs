@@ -0,0 +1,394 @@
+//! Optional 3D view of the same scene, each satellite orbit is turned out of the 2D plane around the earth's axis
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::view::RenderLayers,
+    ui::IsDefaultUiCamera,
+};
+
+use crate::{
+    camera::CameraMovementSet,
+    gps::{solve_position_3d, sphere_intersect, RangeMeasurement3d},
+    moveable::MoveableSet,
+    receiver::ClockOffset,
+    satellite::{RangeError, RangeNoise, Satellite},
+    Character, GraphVisibility, Settings,
+};
+
+pub struct View3dPlugin;
+
+impl Plugin for View3dPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_gizmo_group(
+            Gizmos3d,
+            GizmoConfig {
+                render_layers: RenderLayers::layer(LAYER_3D),
+                ..default()
+            },
+        )
+        // dragging and panning only make sense in the 2D view
+        .configure_sets(
+            Update,
+            (CameraMovementSet, MoveableSet).run_if(not(in_3d_view)),
+        )
+        .add_systems(Startup, setup_3d)
+        .add_systems(
+            Update,
+            (
+                switch_view,
+                (spawn_mirrors, update_mirrors, orbit_camera, draw_3d)
+                    .chain()
+                    .run_if(in_3d_view),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Render layer of everything in the 3D view, so it stays out of the 2D camera and the other way around
+pub const LAYER_3D: u8 = 1;
+
+/// Gizmos drawn by the 3D camera only
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct Gizmos3d;
+
+/// Camera circling the character in the 3D view
+#[derive(Component)]
+pub struct OrbitCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+/// Angle the orbit plane of a satellite is turned around the vertical axis through the earth
+#[derive(Component)]
+pub struct OrbitPlane3d(pub f32);
+
+/// 3D entity following a part of the 2D scene
+#[derive(Component, Clone, Copy)]
+enum Mirror3d {
+    Earth,
+    Receiver,
+    Satellite(Entity),
+    /// Range shell of the character around a satellite
+    Shell(Entity),
+}
+
+#[derive(Resource)]
+struct View3dAssets {
+    sphere: Handle<Mesh>,
+    satellite: Handle<StandardMaterial>,
+    shell: Handle<StandardMaterial>,
+}
+
+fn in_3d_view(settings: Res<Settings>) -> bool {
+    settings.view_3d
+}
+
+/// Position of a point of the 2D scene in the 3D view, turned `plane` radians around the earth's axis
+pub fn to_3d(settings: &Settings, position: Vec2, plane: f32) -> Vec3 {
+    settings.earth_centre.extend(0.)
+        + Quat::from_rotation_y(plane) * (position - settings.earth_centre).extend(0.)
+}
+
+fn setup_3d(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let layer = RenderLayers::layer(LAYER_3D);
+    let sphere = meshes.add(Sphere::new(1.).mesh().uv(64, 32));
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                order: 1,
+                is_active: false,
+                ..default()
+            },
+            projection: PerspectiveProjection {
+                far: 500000.,
+                ..default()
+            }
+            .into(),
+            ..default()
+        },
+        OrbitCamera {
+            yaw: 0.,
+            pitch: 0.3,
+            distance: 30000.,
+        },
+        layer,
+    ));
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: 5000.,
+                ..default()
+            },
+            transform: Transform::from_xyz(1., 2., 1.5).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        layer,
+    ));
+    commands.spawn((
+        PbrBundle {
+            mesh: sphere.clone(),
+            material: materials.add(Color::rgb(0.25, 0.5, 0.3)),
+            ..default()
+        },
+        Mirror3d::Earth,
+        layer,
+    ));
+    commands.spawn((
+        PbrBundle {
+            mesh: sphere.clone(),
+            material: materials.add(Color::RED),
+            transform: Transform::from_scale(Vec3::splat(80.)),
+            ..default()
+        },
+        Mirror3d::Receiver,
+        layer,
+    ));
+    commands.insert_resource(View3dAssets {
+        sphere,
+        satellite: materials.add(Color::rgb(0.8, 0.8, 0.85)),
+        shell: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.3, 0.5, 1., 0.12),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            cull_mode: None,
+            double_sided: true,
+            ..default()
+        }),
+    });
+}
+
+/// Turns on the camera of the current view and moves the UI to it
+fn switch_view(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut camera_query: Query<(Entity, &mut Camera, Has<OrbitCamera>)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (entity, mut camera, is_3d) in camera_query.iter_mut() {
+        let active = is_3d == settings.view_3d;
+        if camera.is_active != active {
+            camera.is_active = active;
+        }
+        if active {
+            commands.entity(entity).insert(IsDefaultUiCamera);
+        } else {
+            commands.entity(entity).remove::<IsDefaultUiCamera>();
+        }
+    }
+}
+
+fn spawn_mirrors(
+    mut commands: Commands,
+    assets: Res<View3dAssets>,
+    mut count: Local<u32>,
+    query: Query<Entity, (With<Satellite>, Without<OrbitPlane3d>)>,
+) {
+    for satellite in query.iter() {
+        // golden angle, spreads the planes evenly however many satellites there are
+        let plane = *count as f32 * 2.399_963;
+        *count += 1;
+        commands.entity(satellite).try_insert(OrbitPlane3d(plane));
+        for mirror in [Mirror3d::Satellite(satellite), Mirror3d::Shell(satellite)] {
+            let material = match mirror {
+                Mirror3d::Shell(_) => assets.shell.clone(),
+                _ => assets.satellite.clone(),
+            };
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.sphere.clone(),
+                    material,
+                    ..default()
+                },
+                mirror,
+                RenderLayers::layer(LAYER_3D),
+            ));
+        }
+    }
+}
+
+/// Satellite position in the 3D view and the range the character measures to it, `noise` is the character's
+/// random error to it
+fn range_3d(
+    settings: &Settings,
+    receiver: Vec2,
+    clock_offset: f32,
+    satellite: Vec2,
+    plane: &OrbitPlane3d,
+    error: Option<&RangeError>,
+    noise: f32,
+) -> (Vec3, f32) {
+    let p = to_3d(settings, satellite, plane.0);
+    let range = p.distance(receiver.extend(0.))
+        + error.map_or(0., |e| {
+            e.systematic_error(satellite, receiver, settings.local_up(receiver))
+        })
+        + noise
+        + clock_offset;
+    (p, range)
+}
+
+fn update_mirrors(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    // the global transform does not clash with the mirrors, it lags a frame behind at most
+    character_query: Query<(&GlobalTransform, Option<&ClockOffset>, &RangeNoise), With<Character>>,
+    satellite_query: Query<(&Transform, &OrbitPlane3d, Option<&RangeError>), Without<Mirror3d>>,
+    mut mirror_query: Query<(Entity, &Mirror3d, &mut Transform, &mut Visibility)>,
+) {
+    let Ok((character, offset, noise)) = character_query.get_single() else {
+        return;
+    };
+    let receiver = character.translation().xy();
+    let offset = settings.clock_offset(offset);
+    for (entity, mirror, mut transform, mut vis) in mirror_query.iter_mut() {
+        let satellite = match *mirror {
+            Mirror3d::Earth => {
+                transform.translation = settings.earth_centre.extend(0.);
+                transform.scale = Vec3::splat(settings.earth_radius);
+                *vis = if settings.earth_enabled {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+                continue;
+            }
+            Mirror3d::Receiver => {
+                transform.translation = receiver.extend(0.);
+                continue;
+            }
+            Mirror3d::Satellite(satellite) | Mirror3d::Shell(satellite) => satellite,
+        };
+        let noise = noise.get(satellite);
+        let Ok((satellite, plane, error)) = satellite_query.get(satellite) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let satellite = satellite.translation.xy();
+        let (p, range) = range_3d(&settings, receiver, offset, satellite, plane, error, noise);
+        transform.translation = p;
+        let (scale, visible) = match mirror {
+            Mirror3d::Shell(_) => (
+                range,
+                settings.ranges_visible
+                    && range > 0.
+                    && settings.in_view_3d(receiver.extend(0.), p),
+            ),
+            _ => (120., true),
+        };
+        transform.scale = Vec3::splat(scale.max(f32::EPSILON));
+        *vis = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn orbit_camera(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion_evr: EventReader<MouseMotion>,
+    mut scroll_evr: EventReader<MouseWheel>,
+    character_query: Query<&Transform, (With<Character>, Without<OrbitCamera>)>,
+    mut camera_query: Query<(&mut Transform, &mut OrbitCamera)>,
+) {
+    let drag = motion_evr.read().fold(Vec2::ZERO, |d, ev| d + ev.delta);
+    let zoom = scroll_evr.read().fold(0., |z, ev| {
+        z - ev.y
+            * match ev.unit {
+                MouseScrollUnit::Line => 0.1,
+                MouseScrollUnit::Pixel => 0.001,
+            }
+    });
+    let focus = character_query
+        .get_single()
+        .map_or(Vec3::ZERO, |t| t.translation.xy().extend(0.));
+    for (mut transform, mut camera) in camera_query.iter_mut() {
+        if mouse.pressed(MouseButton::Right) {
+            camera.yaw -= drag.x * 0.005;
+            camera.pitch =
+                (camera.pitch + drag.y * 0.005).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+        }
+        camera.distance = (camera.distance * (1. + zoom)).clamp(1000., 200000.);
+        let rotation = Quat::from_euler(EulerRot::YXZ, camera.yaw, -camera.pitch, 0.);
+        *transform = Transform::from_translation(focus + rotation * Vec3::Z * camera.distance)
+            .looking_at(focus, Vec3::Y);
+    }
+}
+
+/// Circles where the range shells meet and the fix they give
+fn draw_3d(
+    mut gizmos: Gizmos<Gizmos3d>,
+    settings: Res<Settings>,
+    mut last_fix: Local<Option<Vec3>>,
+    character_query: Query<(&Transform, Option<&ClockOffset>, &RangeNoise), With<Character>>,
+    satellite_query: Query<
+        (Entity, &Transform, &OrbitPlane3d, Option<&RangeError>),
+        With<Satellite>,
+    >,
+) {
+    let Ok((character, offset, noise)) = character_query.get_single() else {
+        return;
+    };
+    let receiver = character.translation.xy();
+    let offset = settings.clock_offset(offset);
+    let measurements: Vec<_> = satellite_query
+        .iter()
+        .map(|(entity, t, plane, error)| {
+            let noise = noise.get(entity);
+            let (satellite, range) = range_3d(
+                &settings,
+                receiver,
+                offset,
+                t.translation.xy(),
+                plane,
+                error,
+                noise,
+            );
+            RangeMeasurement3d { satellite, range }
+        })
+        // the turned orbit can take a satellite behind the earth even when it is in view on the map
+        .filter(|m| settings.in_view_3d(receiver.extend(0.), m.satellite))
+        .collect();
+    if settings.graph_visibility != GraphVisibility::None {
+        for (i, m1) in measurements.iter().enumerate() {
+            for m2 in measurements[i + 1..].iter() {
+                let Some((centre, normal, radius)) =
+                    sphere_intersect(m1.satellite, m1.range, m2.satellite, m2.range)
+                else {
+                    continue;
+                };
+                if let Ok(normal) = Direction3d::new(normal) {
+                    gizmos
+                        .circle(centre, normal, radius, Color::GREEN)
+                        .segments(128);
+                }
+            }
+        }
+    }
+    let initial = last_fix.unwrap_or(settings.earth_centre.extend(0.));
+    *last_fix =
+        solve_position_3d(&measurements, initial, settings.solve_clock_bias).map(|(p, _)| p);
+    if let Some(fix) = *last_fix {
+        let size = 100.;
+        gizmos.sphere(fix, Quat::IDENTITY, size, Color::YELLOW);
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            gizmos.line(
+                fix - axis * size * 2.,
+                fix + axis * size * 2.,
+                Color::YELLOW,
+            );
+        }
+        gizmos.line(receiver.extend(0.), fix, Color::ORANGE);
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::math::vec2;
+use bevy_gps::{
+    geo::{gga, gpx, nmea_checksum, rmc, GeoReference, UtcTime},
+    track::{Track, TrackPoint},
+};
+
+fn reference() -> GeoReference {
+    GeoReference {
+        origin_latitude: 48.,
+        origin_longitude: 11.5,
+        metres_per_unit: 10.,
+        start_time: 1_704_110_400.,
+    }
+}
+
+#[test]
+fn known_checksum() {
+    let body = "GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,";
+    assert_eq!(nmea_checksum(body), 0x47);
+}
+
+#[test]
+fn maps_world_to_lat_lon() {
+    let reference = reference();
+    assert_eq!(reference.to_lat_lon(vec2(0., 0.)), (48., 11.5));
+    // +y is height, not north
+    assert_eq!(reference.to_lat_lon(vec2(0., 185.3)), (48., 11.5));
+    assert!((reference.altitude(vec2(0., 185.3)) - 1853.).abs() < 1e-3);
+    // a minute of longitude is a nautical mile shrunk by the latitude
+    let (lat, lon) = reference.to_lat_lon(vec2(1853. * 48f32.to_radians().cos() / 10., 0.));
+    assert_eq!(lat, 48.);
+    assert!(((lon - 11.5) * 60. - 1.).abs() < 1e-3);
+    assert!((reference.east_of(lon) - 124.).abs() < 0.1);
+    let (_, lon) = reference.to_lat_lon(vec2(-100., 0.));
+    assert!(lon < 11.5);
+}
+
+#[test]
+fn sim_time_to_utc() {
+    assert_eq!(
+        reference().utc(3661.5),
+        UtcTime {
+            year: 2024,
+            month: 1,
+            day: 1,
+            hour: 13,
+            minute: 1,
+            second: 1.5,
+        }
+    );
+    assert_eq!(reference().utc(86400. * 31.).month, 2);
+    // seconds that round up to 60 carry into the minute
+    let t = reference().utc(59.998);
+    assert_eq!((t.hour, t.minute, t.second), (12, 1, 0.));
+    let t = reference().utc(43_199.996);
+    assert_eq!((t.day, t.hour, t.minute, t.second), (2, 0, 0, 0.));
+}
+
+#[test]
+fn writes_sentences() {
+    let reference = reference();
+    let sentence = gga(&reference, 0., Some(vec2(0., 0.)), 4, Some(1.25));
+    assert!(sentence.starts_with("$GPGGA,120000.00,4800.0000,N,01130.0000,E,1,04,1.2,"));
+    let (body, checksum) = sentence[1..].trim_end().split_once('*').unwrap();
+    assert_eq!(
+        u8::from_str_radix(checksum, 16).unwrap(),
+        nmea_checksum(body)
+    );
+
+    // minutes that round up to 60 carry into the degrees
+    let on_degree = GeoReference {
+        origin_longitude: 12.,
+        ..reference.clone()
+    };
+    let sentence = gga(&on_degree, 0., Some(vec2(-1e-4, 50.)), 4, None);
+    assert!(sentence.contains(",4800.0000,N,01200.0000,E,1,04,,500.0,M,"));
+
+    let sentence = gga(&reference, 59.998, None, 0, None);
+    assert!(sentence.starts_with("$GPGGA,120100.00,"));
+
+    let sentence = rmc(&reference, 0., None, vec2(0., 0.));
+    assert!(sentence.starts_with("$GPRMC,120000.00,V,,,,,"));
+    assert!(sentence.contains(",010124,"));
+}
+
+#[test]
+fn gpx_splits_estimate_where_fix_drops() {
+    let point = |time: f32, estimate| TrackPoint {
+        time,
+        truth: vec2(time, 0.),
+        estimate,
+        satellites: 3,
+        hdop: None,
+    };
+    let track = Track {
+        points: vec![
+            point(0., Some(vec2(0., 1.))),
+            point(1., None),
+            point(2., Some(vec2(2., 1.))),
+        ],
+    };
+    let gpx = gpx(&reference(), &[&track]);
+    assert_eq!(gpx.matches("<trk>").count(), 2);
+    assert_eq!(gpx.matches("<trkseg>").count(), 3);
+    assert_eq!(gpx.matches("<trkpt").count(), 5);
+}
@@ -0,0 +1,141 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use bevy::math::{vec2, vec3};
+use bevy_gps::{
+    gps::{
+        elevation, ionosphere_obliquity, line_of_sight, range_difference_hyperbola,
+        solve_position_3d, sphere_intersect, RangeMeasurement3d,
+    },
+    view3d::to_3d,
+    Settings,
+};
+
+#[test]
+fn hyperbola_keeps_range_difference() {
+    let p1 = vec2(-300., 100.);
+    let p2 = vec2(500., -200.);
+    let points = range_difference_hyperbola(p1, 700., p2, 400., 2000., 64).unwrap();
+    assert_eq!(points.len(), 64);
+    for p in points {
+        assert!((p.distance(p1) - p.distance(p2) - 300.).abs() < 0.5);
+    }
+}
+
+#[test]
+fn impossible_range_difference() {
+    // the ranges do not fit the satellite spacing, like with a lot of noise
+    assert!(
+        range_difference_hyperbola(vec2(0., 0.), 10., vec2(100., 0.), 900., 1000., 32).is_none()
+    );
+    assert!(
+        range_difference_hyperbola(vec2(0., 0.), 100., vec2(0., 0.), 100., 1000., 32).is_none()
+    );
+}
+
+#[test]
+fn range_difference_of_the_full_spacing() {
+    // the branch flattens into the ray behind the far satellite, the offset pushed one range below zero
+    let p1 = vec2(0., 0.);
+    let p2 = vec2(300., 0.);
+    let points = range_difference_hyperbola(p1, 250., p2, -50., 1000., 32).unwrap();
+    assert!(points[0].distance(p2) < 0.1);
+    for p in points {
+        assert!(p.is_finite());
+        assert!((p.distance(p1) - p.distance(p2) - 300.).abs() < 0.5);
+    }
+}
+
+#[test]
+fn earth_blocks_satellites_below_horizon() {
+    let centre = vec2(0., -6000.);
+    // standing on top of the earth
+    assert!(line_of_sight(
+        vec2(0., 0.),
+        vec2(500., 2000.),
+        centre,
+        6000.
+    ));
+    assert!(line_of_sight(vec2(0., 0.), vec2(5000., 0.), centre, 6000.));
+    assert!(!line_of_sight(
+        vec2(0., 0.),
+        vec2(5000., -3000.),
+        centre,
+        6000.
+    ));
+    assert!(!line_of_sight(
+        vec2(0., 0.),
+        vec2(0., -13000.),
+        centre,
+        6000.
+    ));
+}
+
+#[test]
+fn earth_blocks_satellites_turned_out_of_the_plane() {
+    let mut settings = Settings {
+        earth_enabled: true,
+        ..Settings::default()
+    };
+    // on the side of the earth, with a satellite straight up on the map
+    let receiver = vec2(6000., -6000.);
+    let satellite = vec2(9000., -6000.);
+    assert!(settings.in_view(receiver, satellite));
+    assert!(settings.in_view_3d(receiver.extend(0.), to_3d(&settings, satellite, 0.)));
+    // half a turn puts it on the far side
+    let turned = to_3d(&settings, satellite, PI);
+    assert!(!settings.in_view_3d(receiver.extend(0.), turned));
+    settings.earth_enabled = false;
+    assert!(settings.in_view_3d(receiver.extend(0.), turned));
+}
+
+#[test]
+fn elevation_from_local_up() {
+    let up = vec2(0., 1.);
+    assert!((elevation(vec2(0., 0.), vec2(0., 100.), up) - FRAC_PI_2).abs() < 1e-4);
+    assert!(elevation(vec2(0., 0.), vec2(100., 0.), up).abs() < 1e-4);
+    assert!((elevation(vec2(0., 0.), vec2(100., 100.), up) - FRAC_PI_4).abs() < 1e-4);
+    assert!(elevation(vec2(0., 0.), vec2(100., -10.), up) < 0.);
+}
+
+#[test]
+fn solves_position_in_3d() {
+    let receiver = vec3(100., 50., -200.);
+    let satellites = [
+        vec3(-3000., 9000., 500.),
+        vec3(4000., 8000., -2000.),
+        vec3(500., 7000., 4000.),
+        vec3(-1000., 10000., -5000.),
+    ];
+    let measurements: Vec<_> = satellites
+        .iter()
+        .map(|s| RangeMeasurement3d {
+            satellite: *s,
+            range: s.distance(receiver) + 75.,
+        })
+        .collect();
+    let (fix, bias) = solve_position_3d(&measurements, vec3(0., 0., 0.), true).unwrap();
+    assert!(fix.distance(receiver) < 0.5);
+    assert!((bias - 75.).abs() < 0.5);
+    assert!(solve_position_3d(&measurements[..3], vec3(0., 0., 0.), true).is_none());
+}
+
+#[test]
+fn spheres_meet_in_a_circle() {
+    let (centre, normal, radius) =
+        sphere_intersect(vec3(0., 0., 0.), 5., vec3(8., 0., 0.), 5.).unwrap();
+    assert!(centre.distance(vec3(4., 0., 0.)) < 1e-4);
+    assert!(normal.distance(vec3(1., 0., 0.)) < 1e-4);
+    assert!((radius - 3.).abs() < 1e-4);
+    assert!(sphere_intersect(vec3(0., 0., 0.), 1., vec3(8., 0., 0.), 1.).is_none());
+}
+
+#[test]
+fn ionosphere_obliquity_follows_local_up() {
+    // straight overhead is the shortest path, whichever way up is
+    let up = vec2(1., 1.).normalize();
+    assert!((ionosphere_obliquity(up * 1000., vec2(0., 0.), up) - 1.).abs() < 1e-4);
+    assert!((ionosphere_obliquity(vec2(0., 1000.), vec2(0., 0.), vec2(0., 1.)) - 1.).abs() < 1e-4);
+    // on the horizon of a tilted up it is the longest
+    let horizon = ionosphere_obliquity(vec2(1., -1.) * 1000., vec2(0., 0.), up);
+    assert!(horizon > 2.9);
+}
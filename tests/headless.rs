@@ -0,0 +1,294 @@
+use bevy::{math::vec2, prelude::*};
+use bevy_gps::{
+    clock::SimClock,
+    dgps::{BaseStation, DgpsEstimate},
+    floppy::FloppyBody,
+    gps::{dilution_of_precision, solve_position, solve_position_and_bias, RangeMeasurement},
+    headless_app,
+    orbit::Orbit,
+    receiver::ClockOffset,
+    satellite::{ErrorSource, RangeError, RangeNoise, Satellite},
+    scatter::FixScatter,
+    solver::PositionEstimate,
+    Settings,
+};
+
+const SATELLITES: [Vec2; 3] = [
+    Vec2::new(-900., 600.),
+    Vec2::new(950., 500.),
+    Vec2::new(50., -1000.),
+];
+
+fn measurements(receiver: Vec2, offset: f32) -> Vec<RangeMeasurement> {
+    SATELLITES
+        .iter()
+        .map(|s| RangeMeasurement {
+            satellite: *s,
+            range: s.distance(receiver) + offset,
+            variance: 1.,
+        })
+        .collect()
+}
+
+fn spawn_scene(app: &mut App, receiver: Vec2) -> Entity {
+    for satellite in SATELLITES {
+        app.world.spawn((
+            TransformBundle::from_transform(Transform::from_translation(satellite.extend(1.))),
+            InheritedVisibility::VISIBLE,
+            Satellite,
+            RangeError::new(vec![]),
+        ));
+    }
+    app.world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(receiver.extend(0.))),
+            FloppyBody::default(),
+            RangeNoise::default(),
+            PositionEstimate::default(),
+            FixScatter::default(),
+        ))
+        .id()
+}
+
+/// Advances the paused simulation clock by `seconds` in a single frame
+fn step_clock(app: &mut App, seconds: f32) {
+    let mut clock = app.world.resource_mut::<SimClock>();
+    clock.paused = true;
+    clock.step_size = seconds;
+    clock.step();
+    app.update();
+}
+
+#[test]
+fn solves_exact_ranges() {
+    let receiver = vec2(120., -40.);
+    let fix = solve_position(&measurements(receiver, 0.), Vec2::ZERO).unwrap();
+    assert!(fix.distance(receiver) < 0.1);
+}
+
+#[test]
+fn recovers_clock_bias() {
+    let receiver = vec2(-300., 200.);
+    let (fix, bias) = solve_position_and_bias(&measurements(receiver, 250.), Vec2::ZERO).unwrap();
+    assert!(fix.distance(receiver) < 0.1);
+    assert!((bias - 250.).abs() < 0.1);
+}
+
+#[test]
+fn dop_needs_spread_satellites() {
+    let dop = dilution_of_precision(&SATELLITES, Vec2::ZERO).unwrap();
+    assert!(dop.gdop.is_finite() && dop.gdop >= dop.hdop);
+
+    let lined_up = [vec2(100., 0.), vec2(200., 0.), vec2(300., 0.)];
+    assert!(dilution_of_precision(&lined_up, Vec2::ZERO).is_none());
+}
+
+#[test]
+fn headless_app_estimates_position() {
+    let mut app = headless_app();
+    let receiver = vec2(200., 100.);
+    let player = spawn_scene(&mut app, receiver);
+    app.update();
+
+    let estimate = app.world.get::<PositionEstimate>(player).unwrap();
+    assert!(estimate.position.unwrap().distance(receiver) < 0.1);
+    assert!(estimate.covariance.is_some());
+}
+
+#[test]
+fn headless_app_solves_offset() {
+    let mut app = headless_app();
+    app.world.resource_mut::<Settings>().ranges_offset = 150.;
+    app.world.resource_mut::<Settings>().solve_clock_bias = true;
+    let receiver = vec2(-50., 300.);
+    let player = spawn_scene(&mut app, receiver);
+    app.update();
+
+    let estimate = app.world.get::<PositionEstimate>(player).unwrap();
+    assert!(estimate.position.unwrap().distance(receiver) < 0.1);
+    assert!((estimate.clock_bias.unwrap() - 150.).abs() < 0.1);
+}
+
+#[test]
+fn earth_hides_satellites_below_horizon() {
+    let mut app = headless_app();
+    let player = spawn_scene(&mut app, vec2(0., 0.));
+    // a range that far off pulls the fix away whenever it is used
+    let below = app
+        .world
+        .query::<(Entity, &Transform)>()
+        .iter(&app.world)
+        .find(|(_, t)| t.translation.xy() == SATELLITES[2])
+        .unwrap()
+        .0;
+    app.world
+        .entity_mut(below)
+        .insert(RangeError::new(vec![ErrorSource::Bias(400.)]));
+    app.update();
+    let fix = |app: &App| app.world.get::<PositionEstimate>(player).unwrap().position;
+    assert!(fix(&app).unwrap().distance(Vec2::ZERO) > 1.);
+
+    // the third satellite is below the horizon, so two are left for a fix
+    app.world.resource_mut::<Settings>().earth_enabled = true;
+    app.update();
+    assert!(fix(&app).unwrap().distance(Vec2::ZERO) < 0.1);
+
+    app.world.resource_mut::<Settings>().solve_clock_bias = true;
+    app.update();
+    assert!(fix(&app).is_none());
+}
+
+#[test]
+fn elevation_mask_drops_low_satellites() {
+    let mut app = headless_app();
+    // only the two satellites high above the receiver are left
+    app.world.resource_mut::<Settings>().elevation_mask = 10f32.to_radians();
+    app.world.resource_mut::<Settings>().solve_clock_bias = true;
+    let player = spawn_scene(&mut app, vec2(0., -200.));
+    app.update();
+    assert!(app
+        .world
+        .get::<PositionEstimate>(player)
+        .unwrap()
+        .position
+        .is_none());
+
+    app.world.resource_mut::<Settings>().solve_clock_bias = false;
+    app.update();
+    let estimate = app.world.get::<PositionEstimate>(player).unwrap();
+    assert!(estimate.position.unwrap().distance(vec2(0., -200.)) < 0.1);
+}
+
+#[test]
+fn receivers_keep_their_own_clock_offsets() {
+    let mut app = headless_app();
+    app.world.resource_mut::<Settings>().solve_clock_bias = true;
+    app.world.resource_mut::<Settings>().ranges_offset = 20.;
+    let first = spawn_scene(&mut app, vec2(-200., 0.));
+    let second = app
+        .world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_xyz(300., 100., 0.)),
+            FloppyBody::default(),
+            RangeNoise::default(),
+            PositionEstimate::default(),
+            ClockOffset(-120.),
+        ))
+        .id();
+    app.update();
+
+    let first = app.world.get::<PositionEstimate>(first).unwrap();
+    assert!((first.clock_bias.unwrap() - 20.).abs() < 0.1);
+    let second = app.world.get::<PositionEstimate>(second).unwrap();
+    assert!(second.position.unwrap().distance(vec2(300., 100.)) < 0.1);
+    assert!((second.clock_bias.unwrap() + 100.).abs() < 0.1);
+}
+
+#[test]
+fn receivers_draw_their_own_noise() {
+    let mut app = headless_app();
+    let first = spawn_scene(&mut app, vec2(100., 50.));
+    let second = app
+        .world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_xyz(100., 50., 0.)),
+            FloppyBody::default(),
+            RangeNoise::default(),
+            PositionEstimate::default(),
+        ))
+        .id();
+    let mut query = app.world.query::<&mut RangeError>();
+    for mut error in query.iter_mut(&mut app.world) {
+        *error = RangeError::new(vec![ErrorSource::Gaussian { std_dev: 10. }]);
+    }
+    // the noise is drawn every half second
+    step_clock(&mut app, 0.5);
+
+    let noise = |entity| app.world.get::<RangeNoise>(entity).unwrap().0.clone();
+    let (first, second) = (noise(first), noise(second));
+    assert_eq!(first.len(), SATELLITES.len());
+    for (satellite, sample) in first.iter() {
+        assert_ne!(second.get(satellite), Some(sample));
+    }
+}
+
+#[test]
+fn base_station_removes_shared_errors() {
+    let mut app = headless_app();
+    app.world.resource_mut::<Settings>().dgps_enabled = true;
+    for (satellite, bias) in SATELLITES.iter().zip([60., -80., 35.]) {
+        app.world.spawn((
+            TransformBundle::from_transform(Transform::from_translation(satellite.extend(1.))),
+            InheritedVisibility::VISIBLE,
+            Satellite,
+            RangeError::new(vec![ErrorSource::Bias(bias)]),
+        ));
+    }
+    app.world.spawn((
+        TransformBundle::from_transform(Transform::from_xyz(400., -100., 0.)),
+        BaseStation,
+        RangeNoise::default(),
+    ));
+    let receiver = vec2(-100., 150.);
+    let player = app
+        .world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(receiver.extend(0.))),
+            FloppyBody::default(),
+            RangeNoise::default(),
+            PositionEstimate::default(),
+            DgpsEstimate::default(),
+        ))
+        .id();
+    app.update();
+
+    let uncorrected = app.world.get::<PositionEstimate>(player).unwrap();
+    assert!(uncorrected.position.unwrap().distance(receiver) > 10.);
+    let corrected = app.world.get::<DgpsEstimate>(player).unwrap();
+    assert!(corrected.position.unwrap().distance(receiver) < 0.1);
+
+    // the noise of the two receivers is independent, so it does not cancel
+    let mut query = app.world.query::<&mut RangeError>();
+    for mut error in query.iter_mut(&mut app.world) {
+        error.sources.push(ErrorSource::Gaussian { std_dev: 10. });
+    }
+    // the noise is drawn every half second
+    step_clock(&mut app, 0.5);
+    let corrected = app.world.get::<DgpsEstimate>(player).unwrap();
+    assert!(corrected.position.unwrap().distance(receiver) > 0.1);
+}
+
+#[test]
+fn scatter_builds_up_while_satellites_orbit() {
+    let mut app = headless_app();
+    let player = spawn_scene(&mut app, vec2(100., 50.));
+    let centre = {
+        let mut settings = app.world.resource_mut::<Settings>();
+        settings.scatter_visible = true;
+        settings.orbits_moving = true;
+        settings.earth_centre
+    };
+    for satellite in SATELLITES {
+        let entity = app
+            .world
+            .query_filtered::<(Entity, &Transform), With<Satellite>>()
+            .iter(&app.world)
+            .find(|(_, t)| t.translation.xy() == satellite)
+            .unwrap()
+            .0;
+        app.world
+            .entity_mut(entity)
+            .insert(Orbit::circular_through(centre, satellite));
+    }
+    for _ in 0..10 {
+        step_clock(&mut app, 0.1);
+    }
+    let moved = app
+        .world
+        .query_filtered::<&Transform, With<Satellite>>()
+        .iter(&app.world)
+        .all(|t| !SATELLITES.contains(&t.translation.xy()));
+    assert!(moved);
+    let scatter = app.world.get::<FixScatter>(player).unwrap();
+    assert!(scatter.samples.len() > 2 * scatter.rate);
+}
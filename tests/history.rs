@@ -0,0 +1,129 @@
+use bevy::{input::InputPlugin, math::vec2, prelude::*};
+use bevy_gps::{
+    headless_app,
+    history::{HistoryControl, HistoryPlugin, Spawned},
+    input::InputMap,
+    moveable::{DeleteMoveable, MoveableDropped},
+    satellite::{RangeError, Satellite},
+    Settings,
+};
+
+fn history_app() -> App {
+    let mut app = headless_app();
+    app.add_plugins((AssetPlugin::default(), InputPlugin, HistoryPlugin));
+    app.init_resource::<InputMap>();
+    // bringing a satellite back loads its sprite
+    app.init_asset::<Image>();
+    app.update();
+    app
+}
+
+fn satellite(app: &mut App, position: Vec2) -> Entity {
+    app.world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(position.extend(1.))),
+            Satellite,
+            RangeError::default(),
+        ))
+        .id()
+}
+
+fn control(app: &mut App, control: HistoryControl) {
+    app.world.send_event(control);
+    app.update();
+}
+
+#[test]
+fn undoes_and_redoes_settings() {
+    let mut app = history_app();
+    app.world.resource_mut::<Settings>().ranges_visible = false;
+    app.update();
+    control(&mut app, HistoryControl::Undo);
+    assert!(app.world.resource::<Settings>().ranges_visible);
+    control(&mut app, HistoryControl::Redo);
+    assert!(!app.world.resource::<Settings>().ranges_visible);
+}
+
+#[test]
+fn merges_settings_edits_made_in_a_row() {
+    let mut app = history_app();
+    for _ in 0..10 {
+        app.world.resource_mut::<Settings>().ranges_offset += 5.;
+        app.update();
+    }
+    control(&mut app, HistoryControl::Undo);
+    assert_eq!(app.world.resource::<Settings>().ranges_offset, 0.);
+}
+
+#[test]
+fn undoes_a_drag() {
+    let mut app = history_app();
+    let entity = satellite(&mut app, vec2(100., 0.));
+    app.world.get_mut::<Transform>(entity).unwrap().translation = vec2(300., 50.).extend(1.);
+    app.world.send_event(MoveableDropped {
+        entity,
+        from: vec2(100., 0.),
+        to: vec2(300., 50.),
+    });
+    app.update();
+    control(&mut app, HistoryControl::Undo);
+    let position = app.world.get::<Transform>(entity).unwrap().translation.xy();
+    assert_eq!(position, vec2(100., 0.));
+    control(&mut app, HistoryControl::Redo);
+    let position = app.world.get::<Transform>(entity).unwrap().translation.xy();
+    assert_eq!(position, vec2(300., 50.));
+}
+
+#[test]
+fn undoing_a_spawn_removes_it() {
+    let mut app = history_app();
+    let entity = satellite(&mut app, vec2(100., 0.));
+    app.world.send_event(Spawned(entity));
+    app.update();
+    control(&mut app, HistoryControl::Undo);
+    assert!(app.world.get_entity(entity).is_none());
+}
+
+#[test]
+fn new_edits_drop_the_redo_steps() {
+    let mut app = history_app();
+    app.world.resource_mut::<Settings>().ranges_visible = false;
+    app.update();
+    control(&mut app, HistoryControl::Undo);
+    app.world.resource_mut::<Settings>().scatter_visible = true;
+    app.update();
+    control(&mut app, HistoryControl::Redo);
+    let settings = app.world.resource::<Settings>();
+    assert!(settings.ranges_visible);
+    assert!(settings.scatter_visible);
+}
+
+#[test]
+fn brings_back_a_deleted_satellite() {
+    let mut app = history_app();
+    let entity = satellite(&mut app, vec2(-200., 400.));
+    app.world.send_event(DeleteMoveable(entity));
+    app.update();
+    // the moveable plugin would despawn it at the end of the frame
+    app.world.despawn(entity);
+    let mut query = app.world.query_filtered::<&Transform, With<Satellite>>();
+    control(&mut app, HistoryControl::Undo);
+    let positions: Vec<_> = query.iter(&app.world).map(|t| t.translation.xy()).collect();
+    assert_eq!(positions, vec![vec2(-200., 400.)]);
+    control(&mut app, HistoryControl::Redo);
+    assert_eq!(query.iter(&app.world).count(), 0);
+}
+
+#[test]
+fn loading_a_scene_clears_the_history() {
+    let mut app = history_app();
+    app.world.resource_mut::<Settings>().ranges_visible = false;
+    app.update();
+    // a loaded scenario changes the settings in the same frame
+    app.world.resource_mut::<Settings>().ranges_offset = 50.;
+    control(&mut app, HistoryControl::Clear);
+    control(&mut app, HistoryControl::Undo);
+    let settings = app.world.resource::<Settings>();
+    assert!(!settings.ranges_visible);
+    assert_eq!(settings.ranges_offset, 50.);
+}
@@ -0,0 +1,73 @@
+use bevy::{asset::ron, prelude::*};
+use bevy_gps::input::{Action, InputMap, KeyBinding};
+
+#[test]
+fn bindings_file_overrides_defaults() {
+    let loaded: InputMap =
+        ron::from_str("(bindings: { AddSatellite: [KeyA, Space], ToggleRanges: [] })").unwrap();
+    let mut map = InputMap::default();
+    map.merge(&loaded);
+    assert_eq!(
+        map.keys(Action::AddSatellite),
+        &[
+            KeyBinding::new(KeyCode::KeyA),
+            KeyBinding::new(KeyCode::Space)
+        ]
+    );
+    assert!(map.keys(Action::ToggleRanges).is_empty());
+    assert_eq!(
+        map.keys(Action::CycleGraphs),
+        &[KeyBinding::new(KeyCode::KeyG)]
+    );
+}
+
+#[test]
+fn shipped_bindings_match_defaults() {
+    let text = std::fs::read_to_string("assets/input.keys.ron").unwrap();
+    let shipped: InputMap = ron::from_str(&text).unwrap();
+    assert_eq!(shipped.bindings, InputMap::default().bindings);
+}
+
+#[test]
+fn ctrl_leaves_shortcuts_alone() {
+    let mut map = InputMap::default();
+    map.bind(Action::ResetOffset, vec![KeyCode::KeyZ.into()]);
+    let mut input = ButtonInput::<KeyCode>::default();
+    input.press(KeyCode::KeyZ);
+    assert!(map.just_pressed(&input, Action::ResetOffset));
+    input.press(KeyCode::ControlLeft);
+    assert!(!map.just_pressed(&input, Action::ResetOffset));
+    assert!(!map.pressed(&input, Action::ResetOffset));
+}
+
+#[test]
+fn chords_need_exactly_their_modifiers() {
+    let loaded: InputMap = ron::from_str(
+        "(bindings: { SaveScenario: [CtrlShift(KeyS)], LoadScenario: [Ctrl(KeyL)] })",
+    )
+    .unwrap();
+    assert_eq!(
+        loaded.keys(Action::SaveScenario),
+        &[KeyBinding::ctrl_shift(KeyCode::KeyS)]
+    );
+    let mut input = ButtonInput::<KeyCode>::default();
+    input.press(KeyCode::KeyL);
+    assert!(!loaded.just_pressed(&input, Action::LoadScenario));
+    input.press(KeyCode::ControlRight);
+    assert!(loaded.just_pressed(&input, Action::LoadScenario));
+    input.press(KeyCode::ShiftLeft);
+    assert!(!loaded.just_pressed(&input, Action::LoadScenario));
+}
+
+#[test]
+fn shift_turns_undo_into_redo() {
+    let map = InputMap::default();
+    let mut input = ButtonInput::<KeyCode>::default();
+    input.press(KeyCode::ControlLeft);
+    input.press(KeyCode::KeyZ);
+    assert!(map.just_pressed(&input, Action::Undo));
+    assert!(!map.just_pressed(&input, Action::Redo));
+    input.press(KeyCode::ShiftRight);
+    assert!(!map.just_pressed(&input, Action::Undo));
+    assert!(map.just_pressed(&input, Action::Redo));
+}
@@ -0,0 +1,107 @@
+use bevy::math::{vec2, Vec2};
+use bevy_gps::{
+    geo::{gga, GeoReference},
+    nmea::{parse_sentence, sky_position, NmeaError, NmeaLog, Sentence, LOG_SKY_RADIUS},
+};
+
+#[test]
+fn parses_gga() {
+    let Ok(Sentence::Gga {
+        time,
+        position: Some((lat, lon)),
+        satellites,
+        hdop,
+    }) = parse_sentence("$GPGGA,123519,4807.038,N,01131.000,W,1,08,0.9,545.4,M,46.9,M,,*55")
+    else {
+        panic!("expected a fix");
+    };
+    assert_eq!(time, 12. * 3600. + 35. * 60. + 19.);
+    assert!((lat - (48. + 7.038 / 60.)).abs() < 1e-9);
+    assert!((lon + (11. + 31. / 60.)).abs() < 1e-9);
+    assert_eq!(satellites, Some(8));
+    assert_eq!(hdop, Some(0.9));
+}
+
+#[test]
+fn rejects_bad_sentences() {
+    assert_eq!(
+        parse_sentence("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*48"),
+        Err(NmeaError::Checksum)
+    );
+    assert_eq!(
+        parse_sentence("$GPVTG,054.7,T,034.4,M,005.5,N,010.2,K"),
+        Err(NmeaError::Unsupported("VTG".to_string()))
+    );
+    assert_eq!(parse_sentence("GPGGA"), Err(NmeaError::Malformed));
+    assert_eq!(parse_sentence("$GPGGA,12"), Err(NmeaError::Malformed));
+    // a corrupt talker byte after lossy decoding
+    assert_eq!(
+        parse_sentence("$G\u{FFFD}A,123519"),
+        Err(NmeaError::Malformed)
+    );
+    assert_eq!(
+        parse_sentence("$\u{FFFD}GGA,123519"),
+        Err(NmeaError::Malformed)
+    );
+    assert_eq!(
+        parse_sentence("$GPGGA,1\u{FFFD}00,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,"),
+        Err(NmeaError::Malformed)
+    );
+}
+
+#[test]
+fn parses_gsv_and_void_rmc() {
+    let Ok(Sentence::Gsv {
+        messages,
+        number,
+        satellites,
+    }) = parse_sentence("$GNGSV,3,1,11,03,03,111,00,04,15,270,00,06,01,010,00,13,06,292,,1")
+    else {
+        panic!("expected gsv");
+    };
+    assert_eq!((messages, number, satellites.len()), (3, 1, 4));
+    assert_eq!(satellites[1].azimuth, Some(270.));
+    assert_eq!(satellites[3].snr, None);
+
+    assert!(matches!(
+        parse_sentence("$GPRMC,081836,V,3751.65,S,14507.36,E,000.0,360.0,130998,011.3,E"),
+        Ok(Sentence::Rmc { position: None, .. })
+    ));
+}
+
+#[test]
+fn log_groups_sentences_by_time() {
+    let reference = GeoReference::default();
+    let mut text = String::new();
+    text.push_str("$GPGSV,1,1,02,05,90,000,40,12,00,090,35\r\n");
+    text.push_str(&gga(&reference, 0., Some(vec2(0., 0.)), 2, None));
+    text.push_str(&gga(&reference, 1., Some(vec2(10., 20.)), 2, None));
+    text.push_str("not a sentence\r\n");
+    text.push_str("$GPGSV,1,1,01,05,45,180,40\r\n");
+    let log = NmeaLog::parse(&text);
+
+    assert_eq!(log.epochs.len(), 2);
+    assert_eq!(log.epochs[0].satellites.len(), 2);
+    assert_eq!(log.epochs[1].satellites.len(), 1);
+    let (_, lon) = log.epochs[1].position.unwrap();
+    assert!((reference.east_of(lon) - 10.).abs() < 0.1);
+    assert_eq!(log.epoch_at(0.5).unwrap().time, 0.);
+    assert!(log.epoch_at(5.).is_none());
+}
+
+#[test]
+fn sky_plot_layout() {
+    let receiver = vec2(100., 50.);
+    let at = |up, azimuth, elevation| sky_position(receiver, up, azimuth, elevation) - receiver;
+    // straight overhead is above the receiver, not on it
+    assert!(at(Vec2::Y, 0., 90.).distance(vec2(0., LOG_SKY_RADIUS)) < 1e-2);
+    assert!(at(Vec2::Y, 90., 0.).distance(vec2(LOG_SKY_RADIUS, 0.)) < 1e-2);
+    assert!(at(Vec2::Y, 270., 0.).distance(vec2(-LOG_SKY_RADIUS, 0.)) < 1e-2);
+    // due south stays in the sky
+    let south = at(Vec2::Y, 180., 30.);
+    assert!((south.angle_between(Vec2::Y).to_degrees().abs() - 60.).abs() < 1e-2);
+    assert!((south.length() - LOG_SKY_RADIUS).abs() < 1e-2);
+    // on the side of the earth the sky turns with the receiver
+    assert!(at(Vec2::X, 0., 90.).distance(vec2(LOG_SKY_RADIUS, 0.)) < 1e-2);
+    assert!(at(Vec2::X, 90., 0.).distance(vec2(0., -LOG_SKY_RADIUS)) < 1e-2);
+}
@@ -0,0 +1,100 @@
+use bevy::{math::vec2, prelude::*};
+use bevy_gps::{
+    clock::SimClock,
+    headless_app,
+    orbit::{orbit_period, Orbit},
+    satellite::Satellite,
+    Settings,
+};
+
+#[test]
+fn eccentric_orbit_passes_through_dragged_position() {
+    let mut orbit = Orbit {
+        radius: 7000.,
+        eccentricity: 0.3,
+        periapsis: 0.4,
+        period: 60.,
+        phase: 1.,
+    };
+    let target = vec2(-2500., 5200.);
+    orbit.set_offset(target);
+    assert!(orbit.offset().distance(target) < 0.5);
+    assert!((orbit.period - orbit_period(orbit.radius)).abs() < 1e-3);
+}
+
+#[test]
+fn orbits_only_move_when_enabled() {
+    let mut app = headless_app();
+    let centre = app.world.resource::<Settings>().earth_centre;
+    let start = vec2(300., 800.);
+    let satellite = app
+        .world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(start.extend(1.))),
+            Satellite,
+            Orbit::circular_through(centre, start),
+        ))
+        .id();
+    app.update();
+    let pos = app
+        .world
+        .get::<Transform>(satellite)
+        .unwrap()
+        .translation
+        .xy();
+    assert!(pos.distance(start) < 0.5);
+
+    app.world.resource_mut::<Settings>().orbits_moving = true;
+    app.world.resource_mut::<SimClock>().paused = true;
+    app.update();
+    let paused = app
+        .world
+        .get::<Transform>(satellite)
+        .unwrap()
+        .translation
+        .xy();
+    assert!(paused.distance(pos) < f32::EPSILON);
+
+    app.world.resource_mut::<SimClock>().step();
+    app.update();
+    let pos = app
+        .world
+        .get::<Transform>(satellite)
+        .unwrap()
+        .translation
+        .xy();
+    assert!(pos.distance(start) > 0.);
+    assert!((pos.distance(centre) - start.distance(centre)).abs() < 0.5);
+}
+
+#[test]
+fn orbits_stay_above_the_earth() {
+    let mut app = headless_app();
+    let (centre, radius) = {
+        let mut settings = app.world.resource_mut::<Settings>();
+        settings.earth_enabled = true;
+        (settings.earth_centre, settings.earth_radius)
+    };
+    // a random spawn near the origin can be inside the earth
+    let start = vec2(200., -900.);
+    assert!(start.distance(centre) < radius);
+    let satellite = app
+        .world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(start.extend(1.))),
+            Satellite,
+            Orbit::circular_through(centre, start),
+        ))
+        .id();
+    app.update();
+    let orbit = app.world.get::<Orbit>(satellite).unwrap();
+    assert!(orbit.radius >= radius);
+    assert!((orbit.period - orbit_period(orbit.radius)).abs() < 1e-3);
+    let pos = app
+        .world
+        .get::<Transform>(satellite)
+        .unwrap()
+        .translation
+        .xy();
+    assert!(pos.distance(centre) >= radius - 0.5);
+}
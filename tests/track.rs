@@ -0,0 +1,70 @@
+use bevy::{math::vec2, prelude::*};
+use bevy_gps::{
+    clock::SimClock,
+    headless_app,
+    track::{Track, TrackControl, TrackMode, TrackPoint, TrackRecorder},
+};
+
+fn point(time: f32, truth: Vec2) -> TrackPoint {
+    TrackPoint {
+        time,
+        truth,
+        estimate: None,
+        satellites: 0,
+        hdop: None,
+    }
+}
+
+#[test]
+fn track_interpolates_between_points() {
+    let track = Track {
+        points: vec![
+            point(2., vec2(0., 0.)),
+            point(3., vec2(100., 0.)),
+            point(5., vec2(100., 200.)),
+        ],
+    };
+    assert_eq!(track.position_at(0.), Some(vec2(0., 0.)));
+    assert!(track.position_at(0.5).unwrap().distance(vec2(50., 0.)) < 1e-3);
+    assert!(track.position_at(2.).unwrap().distance(vec2(100., 100.)) < 1e-3);
+    assert_eq!(track.position_at(4.), None);
+}
+
+#[test]
+fn records_and_replays_route() {
+    let mut app = headless_app();
+    {
+        let mut clock = app.world.resource_mut::<SimClock>();
+        clock.paused = true;
+        clock.step_size = 0.25;
+    }
+    let receiver = app
+        .world
+        .spawn((TransformBundle::default(), Track::default()))
+        .id();
+    app.world.send_event(TrackControl::Record);
+    for i in 0..4 {
+        app.world
+            .get_mut::<Transform>(receiver)
+            .unwrap()
+            .translation
+            .x = i as f32 * 100.;
+        app.world.resource_mut::<SimClock>().step();
+        app.update();
+    }
+    let track = app.world.get::<Track>(receiver).unwrap();
+    assert_eq!(track.points.len(), 4);
+    assert_eq!(track.points[3].truth, vec2(300., 0.));
+
+    app.world.send_event(TrackControl::Replay);
+    app.update();
+    let pos = app.world.get::<Transform>(receiver).unwrap().translation;
+    assert_eq!(pos.x, 0.);
+    for _ in 0..4 {
+        app.world.resource_mut::<SimClock>().step();
+        app.update();
+    }
+    let pos = app.world.get::<Transform>(receiver).unwrap().translation;
+    assert_eq!(pos.x, 300.);
+    assert!(app.world.resource::<TrackRecorder>().mode == TrackMode::Idle);
+}